@@ -0,0 +1,108 @@
+use chrono_to_ics::api::catalog::CourseCatalog;
+use chrono_to_ics::api::data::TimeTable;
+use chrono_to_ics::api::responses::{
+    AcademicCalendarResponse, Course, CourseResponse, HolidayResponse, SectionResponse,
+    TimeTableResponse,
+};
+use chrono_to_ics::campus::Campus;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+const CATALOG_SIZE: usize = 50_000;
+
+// course ids spread over the catalog so lookups don't all hit its start
+fn course_id(i: usize) -> String {
+    format!("{:024x}", i.wrapping_mul(2_654_435_761) % CATALOG_SIZE)
+}
+
+fn course_response() -> CourseResponse {
+    CourseResponse {
+        courses: (0..CATALOG_SIZE)
+            .map(|i| Course {
+                id: course_id(i),
+                code: format!("CS F{:03}", i % 1000),
+                name: format!("course {}", i),
+            })
+            .collect(),
+    }
+}
+
+// eight courses with a lecture, tutorial and lab each
+fn time_table_response(seed: usize) -> TimeTableResponse {
+    let sections = (0..8)
+        .flat_map(|course| {
+            [("L", "M"), ("T", "W"), ("P", "F")]
+                .into_iter()
+                .map(move |(type_name, day)| SectionResponse {
+                    courseId: course_id(seed * 8 + course),
+                    type_name: type_name.to_string(),
+                    number: 1,
+                    instructors: vec!["instructor".to_string()],
+                    roomTime: vec![format!("CS F{:03}:F102:{}:{}", course, day, course + 1)],
+                })
+        })
+        .collect();
+    TimeTableResponse {
+        id: seed.to_string(),
+        name: "bench".to_string(),
+        acadYear: 2024,
+        semester: Some(1),
+        sections,
+        ..Default::default()
+    }
+}
+
+fn academic_calendar() -> AcademicCalendarResponse {
+    AcademicCalendarResponse {
+        version: 1,
+        semesters: vec![HolidayResponse {
+            acad_year: 2024,
+            semester: 1,
+            classwork_start: "2024-08-05T00:00:00Z".to_string(),
+            classwork_end: "2024-12-06T00:00:00Z".to_string(),
+            midsem_start: "2024-10-07T00:00:00Z".to_string(),
+            midsem_end: "2024-10-12T00:00:00Z".to_string(),
+            ..Default::default()
+        }],
+    }
+}
+
+fn bench_catalog(c: &mut Criterion) {
+    let course_response = course_response();
+    let catalog = CourseCatalog::new(course_response.clone());
+    let academic_calendar = academic_calendar();
+    let time_tables = (0..100).map(time_table_response).collect::<Vec<_>>();
+
+    c.bench_function("build catalog", |b| {
+        b.iter_batched(
+            || course_response.clone(),
+            CourseCatalog::new,
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("lookup", |b| {
+        let mut i = 0;
+        b.iter(|| {
+            i += 1;
+            catalog.get(&course_id(i))
+        })
+    });
+    c.bench_function("convert 100 timetables", |b| {
+        b.iter(|| {
+            time_tables
+                .iter()
+                .filter_map(|time_table| {
+                    TimeTable::new(
+                        time_table,
+                        &catalog,
+                        &academic_calendar,
+                        None,
+                        Campus::default(),
+                    )
+                })
+                .count()
+        })
+    });
+}
+
+criterion_group!(benches, bench_catalog);
+criterion_main!(benches);
@@ -0,0 +1,270 @@
+use crate::api::cache::Cache;
+use crate::api::catalog::CourseCatalog;
+use crate::api::data::TimeTable;
+use crate::api::holidays::{saved_override, HolidayOverride, HolidaySource};
+use crate::api::http::{blocking, parse, pick_holidays, ApiError, AsyncHttp, HttpOptions};
+use crate::api::responses::{
+    AcademicCalendarResponse, CourseResponse, HolidayResponse, TimeTableResponse,
+};
+use crate::api::snapshot::Snapshot;
+use crate::campus::Campus;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// [`ApiClient`](crate::api::client::ApiClient) for async code, runs inside a tokio runtime.
+/// The responses are linked into a [`TimeTable`] the same way.
+#[derive(Debug)]
+pub struct AsyncApiClient {
+    pub id: String,
+    campus: Campus,
+    cache: Cache,
+    http: AsyncHttp,
+    /// set when a response was served from the cache because the server was unreachable
+    offline: bool,
+    ttr: Option<TimeTableResponse>,
+    catalog: Option<Arc<CourseCatalog>>,
+    holiday_response: Option<AcademicCalendarResponse>,
+    holiday_source: Option<HolidaySource>,
+    pub holiday_override: Option<HolidayOverride>,
+    /// the saved override couldn't be read, added to the timetable's warnings
+    override_warning: Option<String>,
+    pub timetable: Option<TimeTable>,
+}
+
+impl AsyncApiClient {
+    pub async fn new(id: String) -> Result<Self, ApiError> {
+        Self::with_campus(id, Campus::default()).await
+    }
+    pub async fn with_campus(id: String, campus: Campus) -> Result<Self, ApiError> {
+        Self::with_cache(id, campus, Cache::default()).await
+    }
+    pub async fn with_cache(id: String, campus: Campus, cache: Cache) -> Result<Self, ApiError> {
+        Self::with_http(id, campus, cache, AsyncHttp::new(HttpOptions::default())?).await
+    }
+    /// Fetches the course catalog and the holidays at the same time.
+    pub async fn with_http(
+        id: String,
+        campus: Campus,
+        cache: Cache,
+        http: AsyncHttp,
+    ) -> Result<Self, ApiError> {
+        let mut client = Self::empty(id, campus, cache, http).await;
+        client.fetch(true, true, false).await?;
+        Ok(client)
+    }
+    /// Fetches the course catalog, the holidays and the timetable at the same time.
+    pub async fn with_timetable(
+        id: String,
+        campus: Campus,
+        cache: Cache,
+        http: AsyncHttp,
+    ) -> Result<Self, ApiError> {
+        let mut client = Self::empty(id, campus, cache, http).await;
+        client.fetch(true, true, true).await?;
+        Ok(client)
+    }
+    /// Reuses a catalog another client already fetched, only the holidays are downloaded.
+    pub async fn with_catalog(
+        id: String,
+        campus: Campus,
+        cache: Cache,
+        http: AsyncHttp,
+        catalog: Arc<CourseCatalog>,
+    ) -> Result<Self, ApiError> {
+        let mut client = Self::empty(id, campus, cache, http).await;
+        client.catalog = Some(catalog);
+        client.fetch(false, true, false).await?;
+        Ok(client)
+    }
+    /// Fetches the timetable, and whichever of the catalog and holiday data isn't given,
+    /// at the same time. Given holiday data is reported as [`HolidaySource::Local`].
+    /// `holiday_override` is applied instead of the one saved by the holiday editor.
+    pub async fn with_data(
+        id: String,
+        campus: Campus,
+        cache: Cache,
+        http: AsyncHttp,
+        catalog: Option<Arc<CourseCatalog>>,
+        academic_calendar: Option<AcademicCalendarResponse>,
+        holiday_override: Option<HolidayOverride>,
+    ) -> Result<Self, ApiError> {
+        let mut client = Self::blank(id, campus, cache, http, holiday_override, None);
+        let (courses, holidays) = (catalog.is_none(), academic_calendar.is_none());
+        client.catalog = catalog;
+        if academic_calendar.is_some() {
+            client.holiday_response = academic_calendar;
+            client.holiday_source = Some(HolidaySource::Local);
+        }
+        client.fetch(courses, holidays, true).await?;
+        Ok(client)
+    }
+    async fn empty(id: String, campus: Campus, cache: Cache, http: AsyncHttp) -> Self {
+        let (holiday_override, override_warning) = blocking(saved_override).await;
+        Self::blank(id, campus, cache, http, holiday_override, override_warning)
+    }
+    fn blank(
+        id: String,
+        campus: Campus,
+        cache: Cache,
+        http: AsyncHttp,
+        holiday_override: Option<HolidayOverride>,
+        override_warning: Option<String>,
+    ) -> Self {
+        Self {
+            id,
+            campus,
+            cache,
+            http,
+            offline: false,
+            ttr: None,
+            catalog: None,
+            holiday_response: None,
+            holiday_source: None,
+            holiday_override,
+            override_warning,
+            timetable: None,
+        }
+    }
+    pub fn update_time_table(&mut self) -> Option<()> {
+        self.timetable = TimeTable::new(
+            self.ttr.as_ref()?,
+            self.catalog.as_ref()?,
+            self.holiday_response.as_ref()?,
+            self.holiday_override.as_ref(),
+            self.campus,
+        )
+        .map(|mut time_table| {
+            time_table.warnings.extend(self.override_warning.clone());
+            time_table
+        });
+        Some(())
+    }
+    pub fn campus(&self) -> Campus {
+        self.campus
+    }
+    /// Switches campus, refetching the holiday data of the new one.
+    pub async fn set_campus(&mut self, campus: Campus) -> Result<(), ApiError> {
+        if self.campus != campus {
+            self.campus = campus;
+            self.fetch(false, true, false).await?;
+        }
+        Ok(())
+    }
+    /// Holiday data of the fetched timetable's semester (or the latest one before a
+    /// timetable is fetched) as downloaded, without the local override applied.
+    pub fn holidays(&self) -> Option<&HolidayResponse> {
+        let academic_calendar = self.holiday_response.as_ref()?;
+        self.ttr
+            .as_ref()
+            .and_then(|ttr| academic_calendar.for_time_table(ttr))
+            .or_else(|| academic_calendar.latest())
+    }
+    pub fn academic_calendar(&self) -> Option<&AcademicCalendarResponse> {
+        self.holiday_response.as_ref()
+    }
+    /// The fetched responses, to be saved for offline use.
+    pub fn snapshot(&self) -> Option<Snapshot> {
+        Some(Snapshot::new(
+            self.campus,
+            self.ttr.as_ref()?,
+            self.catalog.as_ref()?,
+            self.holiday_response.as_ref()?,
+        ))
+    }
+    /// The indexed course catalog, cheap to clone and share with other clients.
+    pub fn catalog(&self) -> Option<Arc<CourseCatalog>> {
+        self.catalog.clone()
+    }
+    /// Whether some of the data is a cached copy used because the server was unreachable.
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+    pub fn holiday_source(&self) -> Option<HolidaySource> {
+        self.holiday_source
+    }
+    /// The http client, to share its connections with other clients.
+    pub fn http(&self) -> &AsyncHttp {
+        &self.http
+    }
+    /// Fetches the timetable and holiday data again, the catalog only once the cache ttl
+    /// is over. Holiday data given to [`Self::with_data`] is kept.
+    pub async fn refresh(&mut self) -> Result<(), ApiError> {
+        let holidays = self.holiday_source != Some(HolidaySource::Local);
+        self.offline = false;
+        self.fetch(true, holidays, true).await
+    }
+    pub async fn fetch_timetable(&mut self) -> Result<(), ApiError> {
+        let url = self.http.options().timetable_url(&self.id);
+        let (ttr, stale) =
+            get::<TimeTableResponse>(&self.cache, &self.http, &url, Duration::ZERO).await?;
+        self.ttr = Some(ttr);
+        self.offline |= stale;
+        Ok(())
+    }
+    /// Fetches the catalog, holidays and timetable asked for, all at once.
+    async fn fetch(
+        &mut self,
+        courses: bool,
+        holidays: bool,
+        timetable: bool,
+    ) -> Result<(), ApiError> {
+        let (campus, holidays_url) = (self.campus, self.campus.profile().holidays_url);
+        let course_url = self.http.options().course_url();
+        let timetable_url = self.http.options().timetable_url(&self.id);
+        let (cache, http) = (&self.cache, &self.http);
+        let course_response = async {
+            match courses {
+                true => Some(get::<CourseResponse>(cache, http, &course_url, cache.ttl).await),
+                false => None,
+            }
+        };
+        let holiday_response = async {
+            match holidays {
+                true => Some(match &holidays_url {
+                    Some(url) => get::<AcademicCalendarResponse>(cache, http, url, cache.ttl).await,
+                    None => Err(ApiError::NoHolidayData(campus)),
+                }),
+                false => None,
+            }
+        };
+        // timetables get edited, so always ask whether the cached one is still current
+        let time_table_response = async {
+            match timetable {
+                true => Some(
+                    get::<TimeTableResponse>(cache, http, &timetable_url, Duration::ZERO).await,
+                ),
+                false => None,
+            }
+        };
+        let (course_response, holiday_response, time_table_response) =
+            tokio::join!(course_response, holiday_response, time_table_response);
+        if let Some(course_response) = course_response {
+            let (course_response, stale) = course_response?;
+            self.catalog = Some(Arc::new(CourseCatalog::new(course_response)));
+            self.offline |= stale;
+        }
+        if let Some(holiday_response) = holiday_response {
+            let (holiday_response, source, stale) = pick_holidays(self.campus, holiday_response)?;
+            self.holiday_response = Some(holiday_response);
+            self.holiday_source = Some(source);
+            self.offline |= stale;
+        }
+        if let Some(time_table_response) = time_table_response {
+            let (ttr, stale) = time_table_response?;
+            self.ttr = Some(ttr);
+            self.offline |= stale;
+        }
+        Ok(())
+    }
+}
+
+async fn get<T: DeserializeOwned>(
+    cache: &Cache,
+    http: &AsyncHttp,
+    url: &str,
+    max_age: Duration,
+) -> Result<(T, bool), ApiError> {
+    let (body, status) = cache.get_async(http, url, max_age).await?;
+    parse(&body, status)
+}
@@ -0,0 +1,319 @@
+//! Builds a [`TimeTable`] directly, without going through the chrono api responses.
+use super::data::{
+    Closure, Course, Holiday, HolidayKind, Section, TimeTable, TimeTableChange, Timing,
+};
+use crate::campus::Campus;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc, Weekday};
+
+impl Timing {
+    /// A class in `classroom` on `day`, from slot `start` to slot `end` (both inclusive).
+    pub fn new(day: Weekday, classroom: impl Into<String>, start: u8, end: u8) -> Self {
+        Self {
+            day,
+            classroom: classroom.into(),
+            start,
+            end,
+        }
+    }
+}
+
+impl Section {
+    pub fn new(number: i32) -> Self {
+        Self {
+            number,
+            instructors: vec![],
+            timings: vec![],
+        }
+    }
+    pub fn instructor(mut self, name: impl Into<String>) -> Self {
+        self.instructors.push(name.into());
+        self
+    }
+    /// Adds a timing, merged with the ones right before or after it on the same day.
+    pub fn timing(mut self, timing: Timing) -> Self {
+        self.timings.push(timing);
+        self.optimize_timings();
+        self
+    }
+}
+
+impl Course {
+    pub fn builder(code: impl Into<String>, name: impl Into<String>) -> CourseBuilder {
+        let code = code.into();
+        CourseBuilder {
+            course: Course {
+                id: code.clone(),
+                code,
+                name: name.into(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+pub struct CourseBuilder {
+    course: Course,
+}
+impl CourseBuilder {
+    /// id of the course in the chrono catalog, the course code by default
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.course.id = id.into();
+        self
+    }
+    pub fn lecture(mut self, section: Section) -> Self {
+        self.course.lecture = Some(section);
+        self
+    }
+    pub fn tutorial(mut self, section: Section) -> Self {
+        self.course.tutorial = Some(section);
+        self
+    }
+    pub fn lab(mut self, section: Section) -> Self {
+        self.course.lab = Some(section);
+        self
+    }
+    pub fn midsem(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.course.midsem_date_time = Some((start, end));
+        self
+    }
+    pub fn compre(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.course.compre_date_time = Some((start, end));
+        self
+    }
+    pub fn build(self) -> Course {
+        self.course
+    }
+}
+
+impl Holiday {
+    /// A full day institute holiday.
+    pub fn new(name: impl Into<String>, date: DateTime<Utc>) -> Self {
+        Self {
+            name: name.into(),
+            date,
+            ..Default::default()
+        }
+    }
+    pub fn kind(mut self, kind: HolidayKind) -> Self {
+        self.kind = kind;
+        self
+    }
+    pub fn closure(mut self, closure: Closure) -> Self {
+        self.closure = closure;
+        self
+    }
+}
+
+impl TimeTable {
+    /// Classwork runs from `classwork_start` to `classwork_end`, both campus-local dates.
+    pub fn builder(
+        campus: Campus,
+        classwork_start: NaiveDate,
+        classwork_end: NaiveDate,
+    ) -> TimeTableBuilder {
+        TimeTableBuilder {
+            campus,
+            classwork: (classwork_start, classwork_end),
+            midsem: None,
+            id: String::new(),
+            name: String::new(),
+            acad_year: 0,
+            courses: vec![],
+            holidays: vec![],
+            day_swaps: vec![],
+        }
+    }
+}
+
+/// Dates are campus-local, they are stored as midnight on campus like holidays.json does.
+pub struct TimeTableBuilder {
+    campus: Campus,
+    classwork: (NaiveDate, NaiveDate),
+    midsem: Option<(NaiveDate, NaiveDate)>,
+    id: String,
+    name: String,
+    acad_year: i32,
+    courses: Vec<Course>,
+    holidays: Vec<Holiday>,
+    day_swaps: Vec<(NaiveDate, Weekday)>,
+}
+impl TimeTableBuilder {
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+    /// 2024 for 2024-25
+    pub fn acad_year(mut self, acad_year: i32) -> Self {
+        self.acad_year = acad_year;
+        self
+    }
+    pub fn midsem(mut self, start: NaiveDate, end: NaiveDate) -> Self {
+        self.midsem = Some((start, end));
+        self
+    }
+    pub fn course(mut self, course: Course) -> Self {
+        self.courses.push(course);
+        self
+    }
+    pub fn holiday(mut self, holiday: Holiday) -> Self {
+        self.holidays.push(holiday);
+        self
+    }
+    /// A full day institute holiday on a campus-local date.
+    pub fn holiday_on(self, name: impl Into<String>, date: NaiveDate) -> Self {
+        match self.midnight(date) {
+            Some(date) => self.holiday(Holiday::new(name, date)),
+            None => self,
+        }
+    }
+    /// `date` follows the timetable of `day`.
+    pub fn day_swap(mut self, date: NaiveDate, day: Weekday) -> Self {
+        self.day_swaps.push((date, day));
+        self
+    }
+    /// `None` when one of the dates doesn't exist on campus, like [`TimeTable::new`].
+    pub fn build(self) -> Option<TimeTable> {
+        let midsem_dates = match self.midsem {
+            Some((start, end)) => Some((self.midnight(start)?, self.midnight(end)?)),
+            None => None,
+        };
+        let time_table_changes = self
+            .day_swaps
+            .iter()
+            .map(|(date, day)| {
+                Some(TimeTableChange {
+                    day: *day,
+                    date: self.midnight(*date)?,
+                })
+            })
+            .collect::<Option<Vec<TimeTableChange>>>()?;
+        Some(TimeTable {
+            classwork_start: self.midnight(self.classwork.0)?,
+            classwork_end: self.midnight(self.classwork.1)?,
+            id: self.id,
+            name: self.name,
+            acad_year: self.acad_year,
+            campus: self.campus,
+            midsem_dates,
+            courses: self.courses,
+            holidays: self.holidays,
+            warnings: vec![],
+            time_table_changes,
+        })
+    }
+    fn midnight(&self, date: NaiveDate) -> Option<DateTime<Utc>> {
+        self.campus.profile().to_utc(date, NaiveTime::MIN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn builds_courses() {
+        let course = Course::builder("CS F111", "Computer Programming")
+            .lecture(
+                Section::new(1)
+                    .instructor("A")
+                    .timing(Timing::new(Weekday::Mon, "F102", 2, 2))
+                    .timing(Timing::new(Weekday::Wed, "F102", 2, 2))
+                    .timing(Timing::new(Weekday::Mon, "F102", 3, 3)),
+            )
+            .build();
+        // the catalog id is the code unless given
+        assert_eq!(course.id, "CS F111");
+        assert_eq!(course.tutorial, None);
+        let lecture = course.lecture.unwrap();
+        assert_eq!(lecture.instructors, vec!["A"]);
+        assert_eq!(
+            lecture.timings,
+            vec![
+                Timing::new(Weekday::Mon, "F102", 2, 3),
+                Timing::new(Weekday::Wed, "F102", 2, 2),
+            ]
+        );
+        let slots = Campus::Hyderabad.profile().slots;
+        assert_eq!(
+            (slots.start(2), slots.end(3)),
+            (
+                NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(10, 50, 0).unwrap()
+            )
+        );
+
+        let course = Course::builder("CS F111", "Computer Programming")
+            .id("c1")
+            .build();
+        assert_eq!(
+            (course.id.as_str(), course.code.as_str()),
+            ("c1", "CS F111")
+        );
+    }
+
+    #[test]
+    fn holidays_close_the_whole_day_by_default() {
+        let date = Utc.with_ymd_and_hms(2024, 10, 30, 18, 30, 0).unwrap();
+        let holiday = Holiday::new("Diwali", date);
+        assert_eq!(
+            (holiday.kind, holiday.closure),
+            (HolidayKind::Institute, Closure::FullDay)
+        );
+        let holiday = holiday
+            .kind(HolidayKind::Restricted)
+            .closure(Closure::BeforeSlot(5));
+        assert_eq!((holiday.name.as_str(), holiday.date), ("Diwali", date));
+        assert_eq!(
+            (holiday.kind, holiday.closure),
+            (HolidayKind::Restricted, Closure::BeforeSlot(5))
+        );
+    }
+
+    #[test]
+    fn dates_are_midnight_on_campus() {
+        let time_table = TimeTable::builder(Campus::Hyderabad, date(8, 5), date(11, 29))
+            .midsem(date(10, 7), date(10, 12))
+            .holiday_on("Janmashtami", date(8, 26))
+            .day_swap(date(9, 14), Weekday::Mon)
+            .build()
+            .unwrap();
+        // midnight in india is 18:30 utc the day before
+        let ist = |month: u32, day: u32| {
+            Utc.with_ymd_and_hms(2024, month, day - 1, 18, 30, 0)
+                .unwrap()
+        };
+        assert_eq!(time_table.classwork_start, ist(8, 5));
+        assert_eq!(time_table.classwork_end, ist(11, 29));
+        assert_eq!(time_table.midsem_dates, Some((ist(10, 7), ist(10, 12))));
+        assert_eq!(
+            time_table.holidays,
+            vec![Holiday::new("Janmashtami", ist(8, 26))]
+        );
+        assert_eq!(
+            time_table.time_table_changes,
+            vec![TimeTableChange {
+                day: Weekday::Mon,
+                date: ist(9, 14),
+            }]
+        );
+        assert_eq!((time_table.id.as_str(), time_table.acad_year), ("", 0));
+        assert!(time_table.courses.is_empty() && time_table.warnings.is_empty());
+
+        let time_table = TimeTable::builder(Campus::Dubai, date(8, 5), date(11, 29))
+            .build()
+            .unwrap();
+        assert_eq!(
+            time_table.classwork_start,
+            Utc.with_ymd_and_hms(2024, 8, 4, 20, 0, 0).unwrap()
+        );
+    }
+}
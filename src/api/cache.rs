@@ -0,0 +1,397 @@
+#[cfg(feature = "http-blocking")]
+use super::http::Http;
+#[cfg(feature = "http-async")]
+use super::http::{blocking, AsyncHttp};
+use chrono::{DateTime, Utc};
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Api responses kept on disk, so the course catalog is not downloaded on every run.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    pub dir: PathBuf,
+    /// how long a response is used without asking the server whether it changed
+    pub ttl: Duration,
+    /// revalidate every response, ignoring the ttl
+    pub refresh: bool,
+}
+
+/// Where a response came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// cached and younger than the ttl
+    Fresh,
+    /// cached, the server said it is unchanged
+    Revalidated,
+    Downloaded,
+    /// cached and the server could not be reached
+    Stale,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    url: String,
+    fetched_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    body: String,
+}
+
+impl Default for Cache {
+    // $XDG_CACHE_HOME/chrono-to-ics, responses are reused for a day
+    fn default() -> Self {
+        Self::new(
+            dirs::cache_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join("chrono-to-ics"),
+        )
+    }
+}
+/// $XDG_DATA_HOME/chrono-to-ics, for the files that can't be downloaded again, like the
+/// holiday override.
+pub fn data_dir() -> PathBuf {
+    // never relative to wherever the program happens to run
+    dirs::data_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("chrono-to-ics")
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            refresh: false,
+        }
+    }
+    /// Body of `url`, from the cache when it is younger than `max_age` (and the ttl),
+    /// revalidated with the server otherwise. Falls back to the cached copy when offline.
+    #[cfg(feature = "http-blocking")]
+    pub fn get(
+        &self,
+        http: &Http,
+        url: &str,
+        max_age: Duration,
+    ) -> Result<(String, CacheStatus), reqwest::Error> {
+        let revalidation = match self.lookup(url, max_age) {
+            Lookup::Fresh(body) => return Ok((body, CacheStatus::Fresh)),
+            Lookup::Revalidate(revalidation) => revalidation,
+        };
+        let request = http.get(url).headers(revalidation.headers());
+        let response = match http.send(request) {
+            Ok(response) => response,
+            Err(e) => return revalidation.offline(e),
+        };
+        let revalidation = match revalidation.settle(response.status()) {
+            Ok(settled) => return Ok(settled.save()),
+            Err(revalidation) => revalidation,
+        };
+        let response = response.error_for_status()?;
+        let headers = response.headers().clone();
+        Ok(revalidation.store(&headers, response.text()?).save())
+    }
+    /// [`Cache::get`] for the async client.
+    #[cfg(feature = "http-async")]
+    pub async fn get_async(
+        &self,
+        http: &AsyncHttp,
+        url: &str,
+        max_age: Duration,
+    ) -> Result<(String, CacheStatus), reqwest::Error> {
+        let (cache, owned_url) = (self.clone(), url.to_string());
+        let lookup = blocking(move || cache.lookup(&owned_url, max_age)).await;
+        let revalidation = match lookup {
+            Lookup::Fresh(body) => return Ok((body, CacheStatus::Fresh)),
+            Lookup::Revalidate(revalidation) => revalidation,
+        };
+        let request = http.get(url).headers(revalidation.headers());
+        let response = match http.send(request).await {
+            Ok(response) => response,
+            Err(e) => return revalidation.offline(e),
+        };
+        let revalidation = match revalidation.settle(response.status()) {
+            Ok(settled) => return Ok(blocking(move || settled.save()).await),
+            Err(revalidation) => revalidation,
+        };
+        let response = response.error_for_status()?;
+        let headers = response.headers().clone();
+        let settled = revalidation.store(&headers, response.text().await?);
+        Ok(blocking(move || settled.save()).await)
+    }
+    fn lookup(&self, url: &str, max_age: Duration) -> Lookup {
+        let path = self.path(url);
+        let entry = read_entry(&path).filter(|entry| entry.url == url);
+        if let Some(entry) = entry.as_ref() {
+            let age = (Utc::now() - entry.fetched_at).to_std().unwrap_or_default();
+            if !self.refresh && age < max_age.min(self.ttl) {
+                return Lookup::Fresh(entry.body.clone());
+            }
+        }
+        Lookup::Revalidate(Revalidation {
+            url: url.to_string(),
+            path,
+            entry,
+        })
+    }
+    /// Deletes every cached response.
+    pub fn clear(&self) -> std::io::Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+    // https://www.chrono.crux-bphc.com/api/course -> www_chrono_crux-bphc_com_api_course.json
+    fn path(&self, url: &str) -> PathBuf {
+        let name = url
+            .split_once("://")
+            .map_or(url, |(_, rest)| rest)
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() || c == '-' {
+                true => c,
+                false => '_',
+            })
+            .collect::<String>();
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+enum Lookup {
+    Fresh(String),
+    Revalidate(Revalidation),
+}
+/// A response the server has to be asked about, with the cached copy if there is one.
+struct Revalidation {
+    url: String,
+    path: PathBuf,
+    entry: Option<CacheEntry>,
+}
+impl Revalidation {
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let Some(entry) = self.entry.as_ref() else {
+            return headers;
+        };
+        let value = |value: &str| HeaderValue::from_str(value).ok();
+        if let Some(etag) = entry.etag.as_deref().and_then(value) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = entry.last_modified.as_deref().and_then(value) {
+            headers.insert(IF_MODIFIED_SINCE, last_modified);
+        }
+        headers
+    }
+    /// The server could not be reached.
+    fn offline(self, e: reqwest::Error) -> Result<(String, CacheStatus), reqwest::Error> {
+        self.entry
+            .map(|entry| (entry.body, CacheStatus::Stale))
+            .ok_or(e)
+    }
+    /// The cached copy when `status` says it is still current or the server is down,
+    /// `self` back when the response body is needed.
+    fn settle(self, status: StatusCode) -> Result<Settled, Box<Self>> {
+        match (status, self.entry) {
+            (StatusCode::NOT_MODIFIED, Some(entry)) => Ok(Settled {
+                path: Some(self.path),
+                entry: CacheEntry {
+                    fetched_at: Utc::now(),
+                    ..entry
+                },
+                status: CacheStatus::Revalidated,
+            }),
+            // the server is down, the cached copy is the best there is
+            (status, Some(entry)) if status.is_server_error() => Ok(Settled {
+                path: None,
+                entry,
+                status: CacheStatus::Stale,
+            }),
+            (_, entry) => Err(Box::new(Self { entry, ..self })),
+        }
+    }
+    fn store(self, headers: &HeaderMap, body: String) -> Settled {
+        let header = |name: HeaderName| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let status = match self.entry.is_some_and(|entry| entry.body == body) {
+            true => CacheStatus::Revalidated,
+            false => CacheStatus::Downloaded,
+        };
+        let entry = CacheEntry {
+            url: self.url,
+            fetched_at: Utc::now(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            body,
+        };
+        Settled {
+            path: Some(self.path),
+            entry,
+            status,
+        }
+    }
+}
+
+/// A response to hand back, and where to write it when the cached copy changed.
+struct Settled {
+    path: Option<PathBuf>,
+    entry: CacheEntry,
+    status: CacheStatus,
+}
+impl Settled {
+    fn save(self) -> (String, CacheStatus) {
+        if let Some(path) = &self.path {
+            // a cache that can't be written only costs a download next time
+            let _ = write_entry(path, &self.entry);
+        }
+        (self.entry.body, self.status)
+    }
+}
+
+fn read_entry(path: &Path) -> Option<CacheEntry> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+fn write_entry(path: &Path, entry: &CacheEntry) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string(entry)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_per_url() {
+        let cache = Cache::new("cache");
+        assert_eq!(
+            cache.path("https://www.chrono.crux-bphc.com/api/course"),
+            PathBuf::from("cache/www_chrono_crux-bphc_com_api_course.json")
+        );
+    }
+
+    #[test]
+    fn data_dir_is_absolute() {
+        assert!(data_dir().is_absolute());
+    }
+
+    #[cfg(feature = "server")]
+    mod get {
+        use super::*;
+        use crate::api::http::HttpOptions;
+        use std::sync::{Arc, Mutex};
+        use std::thread;
+
+        const ETAG_V1: &str = "\"v1\"";
+
+        /// Serves "body" with an etag for `requests` requests, answering a matching
+        /// If-None-Match with 304, then goes away. Remembers the If-None-Match of every request.
+        fn origin(requests: usize) -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+            let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+            let addr = server.server_addr().to_ip().unwrap();
+            let seen = Arc::new(Mutex::new(vec![]));
+            let log = seen.clone();
+            thread::spawn(move || {
+                for request in server.incoming_requests().take(requests) {
+                    let if_none_match = request
+                        .headers()
+                        .iter()
+                        .find(|header| header.field.equiv("If-None-Match"))
+                        .map(|header| header.value.to_string());
+                    let status = match if_none_match.as_deref() == Some(ETAG_V1) {
+                        true => 304,
+                        false => 200,
+                    };
+                    log.lock().unwrap().push(if_none_match);
+                    let etag = tiny_http::Header::from_bytes("ETag", ETAG_V1).unwrap();
+                    let response = tiny_http::Response::from_string("body")
+                        .with_status_code(status)
+                        .with_header(etag);
+                    let _ = request.respond(response);
+                }
+            });
+            (format!("http://{}/data", addr), seen)
+        }
+
+        fn cache(name: &str) -> (Cache, Http) {
+            let cache = Cache::new(std::env::temp_dir().join(format!(
+                "chrono-to-ics-cache-{}-{}",
+                name,
+                std::process::id()
+            )));
+            let _ = cache.clear();
+            let http = Http::new(HttpOptions {
+                retries: 0,
+                timeout: Duration::from_secs(2),
+                ..Default::default()
+            })
+            .unwrap();
+            (cache, http)
+        }
+
+        #[test]
+        fn fresh_within_the_ttl() {
+            let (url, seen) = origin(1);
+            let (cache, http) = cache("fresh");
+            let get = || cache.get(&http, &url, Duration::MAX).unwrap();
+            assert_eq!(get(), ("body".to_string(), CacheStatus::Downloaded));
+            assert_eq!(get(), ("body".to_string(), CacheStatus::Fresh));
+            assert_eq!(seen.lock().unwrap().len(), 1);
+        }
+
+        #[test]
+        fn revalidates_with_the_etag_after_the_ttl() {
+            let (url, seen) = origin(2);
+            let (mut cache, http) = cache("revalidate");
+            cache.ttl = Duration::ZERO;
+            let get = || cache.get(&http, &url, Duration::MAX).unwrap();
+            assert_eq!(get(), ("body".to_string(), CacheStatus::Downloaded));
+            assert_eq!(get(), ("body".to_string(), CacheStatus::Revalidated));
+            assert_eq!(*seen.lock().unwrap(), vec![None, Some(ETAG_V1.to_string())]);
+        }
+
+        #[test]
+        fn refresh_revalidates_fresh_responses() {
+            let (url, seen) = origin(2);
+            let (mut cache, http) = cache("refresh");
+            assert_eq!(
+                cache.get(&http, &url, Duration::MAX).unwrap().1,
+                CacheStatus::Downloaded
+            );
+            cache.refresh = true;
+            assert_eq!(
+                cache.get(&http, &url, Duration::MAX).unwrap().1,
+                CacheStatus::Revalidated
+            );
+            assert_eq!(seen.lock().unwrap().len(), 2);
+        }
+
+        #[test]
+        fn stale_copy_when_offline() {
+            let (url, _) = origin(1);
+            let (cache, http) = cache("offline");
+            assert_eq!(
+                cache.get(&http, &url, Duration::ZERO).unwrap().1,
+                CacheStatus::Downloaded
+            );
+            // the origin is gone after its one request
+            assert_eq!(
+                cache.get(&http, &url, Duration::ZERO).unwrap(),
+                ("body".to_string(), CacheStatus::Stale)
+            );
+            cache.clear().unwrap();
+            assert!(cache.get(&http, &url, Duration::ZERO).is_err());
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use super::responses::{Course, CourseResponse};
+use std::collections::HashMap;
+
+/// The course catalog indexed by course id. Built once from `/api/course` and shared by
+/// every timetable converted afterwards.
+#[derive(Debug, Default, Clone)]
+pub struct CourseCatalog {
+    courses: HashMap<String, Course>,
+}
+impl CourseCatalog {
+    pub fn new(course_response: CourseResponse) -> Self {
+        let mut courses = HashMap::with_capacity(course_response.courses.len());
+        // the first entry wins when an id is listed twice, like the old linear search
+        for course in course_response.courses {
+            courses.entry(course.id.clone()).or_insert(course);
+        }
+        Self { courses }
+    }
+    pub fn get(&self, id: &str) -> Option<&Course> {
+        self.courses.get(id)
+    }
+    pub fn len(&self) -> usize {
+        self.courses.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.courses.is_empty()
+    }
+    /// Only the courses with the given ids, in the order asked for and without repeats.
+    pub fn subset<'a>(&self, ids: impl IntoIterator<Item = &'a str>) -> CourseResponse {
+        let mut courses: Vec<Course> = vec![];
+        for id in ids {
+            if let Some(course) = self.get(id) {
+                if !courses.iter().any(|other| other.id == course.id) {
+                    courses.push(course.clone());
+                }
+            }
+        }
+        CourseResponse { courses }
+    }
+}
+impl From<CourseResponse> for CourseCatalog {
+    fn from(course_response: CourseResponse) -> Self {
+        Self::new(course_response)
+    }
+}
+impl From<&CourseResponse> for CourseCatalog {
+    fn from(course_response: &CourseResponse) -> Self {
+        Self::new(course_response.clone())
+    }
+}
@@ -1,66 +1,289 @@
+use crate::api::cache::Cache;
+use crate::api::catalog::CourseCatalog;
 use crate::api::data::TimeTable;
-use crate::api::responses::{CourseResponse, HolidayResponse, TimeTableResponse};
-use reqwest::blocking::Client;
-use reqwest::Error;
+use crate::api::holidays::{saved_override, HolidayOverride, HolidaySource};
+use crate::api::http::{parse, pick_holidays, Http, HttpOptions};
+use crate::api::responses::{
+    AcademicCalendarResponse, CourseResponse, HolidayResponse, TimeTableResponse,
+};
+use crate::api::snapshot::Snapshot;
+use crate::campus::Campus;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+pub use crate::api::http::ApiError;
+
 #[derive(Debug)]
 pub struct ApiClient {
     pub id: String,
+    campus: Campus,
+    cache: Cache,
+    http: Http,
+    /// set when a response was served from the cache because the server was unreachable
+    offline: bool,
     ttr: Option<TimeTableResponse>,
-    cr: Option<CourseResponse>,
-    holiday_response: Option<HolidayResponse>,
+    catalog: Option<Arc<CourseCatalog>>,
+    holiday_response: Option<AcademicCalendarResponse>,
+    holiday_source: Option<HolidaySource>,
+    pub holiday_override: Option<HolidayOverride>,
+    /// the saved override couldn't be read, added to the timetable's warnings
+    override_warning: Option<String>,
     pub timetable: Option<TimeTable>,
 }
 
 impl ApiClient {
-    pub fn new(id: String) -> Result<Self, Error> {
-        let mut client = Self {
+    pub fn new(id: String) -> Result<Self, ApiError> {
+        Self::with_campus(id, Campus::default())
+    }
+    pub fn with_campus(id: String, campus: Campus) -> Result<Self, ApiError> {
+        Self::with_cache(id, campus, Cache::default())
+    }
+    pub fn with_cache(id: String, campus: Campus, cache: Cache) -> Result<Self, ApiError> {
+        Self::with_http(id, campus, cache, Http::new(HttpOptions::default())?)
+    }
+    /// Fetches the course catalog and the holidays at the same time.
+    pub fn with_http(
+        id: String,
+        campus: Campus,
+        cache: Cache,
+        http: Http,
+    ) -> Result<Self, ApiError> {
+        let mut client = Self::empty(id, campus, cache, http);
+        client.fetch(true, true, false)?;
+        Ok(client)
+    }
+    /// Fetches the course catalog, the holidays and the timetable at the same time.
+    pub fn with_timetable(
+        id: String,
+        campus: Campus,
+        cache: Cache,
+        http: Http,
+    ) -> Result<Self, ApiError> {
+        let mut client = Self::empty(id, campus, cache, http);
+        client.fetch(true, true, true)?;
+        Ok(client)
+    }
+    /// Reuses a catalog another client already fetched, only the holidays are downloaded.
+    pub fn with_catalog(
+        id: String,
+        campus: Campus,
+        cache: Cache,
+        http: Http,
+        catalog: Arc<CourseCatalog>,
+    ) -> Result<Self, ApiError> {
+        let mut client = Self::empty(id, campus, cache, http);
+        client.catalog = Some(catalog);
+        client.fetch(false, true, false)?;
+        Ok(client)
+    }
+    /// Fetches the timetable, and whichever of the catalog and holiday data isn't given,
+    /// at the same time. Given holiday data is reported as [`HolidaySource::Local`].
+    /// `holiday_override` is applied instead of the one saved by the holiday editor.
+    pub fn with_data(
+        id: String,
+        campus: Campus,
+        cache: Cache,
+        http: Http,
+        catalog: Option<Arc<CourseCatalog>>,
+        academic_calendar: Option<AcademicCalendarResponse>,
+        holiday_override: Option<HolidayOverride>,
+    ) -> Result<Self, ApiError> {
+        let mut client = Self::blank(id, campus, cache, http, holiday_override, None);
+        let (courses, holidays) = (catalog.is_none(), academic_calendar.is_none());
+        client.catalog = catalog;
+        if academic_calendar.is_some() {
+            client.holiday_response = academic_calendar;
+            client.holiday_source = Some(HolidaySource::Local);
+        }
+        client.fetch(courses, holidays, true)?;
+        Ok(client)
+    }
+    fn empty(id: String, campus: Campus, cache: Cache, http: Http) -> Self {
+        let (holiday_override, override_warning) = saved_override();
+        Self::blank(id, campus, cache, http, holiday_override, override_warning)
+    }
+    fn blank(
+        id: String,
+        campus: Campus,
+        cache: Cache,
+        http: Http,
+        holiday_override: Option<HolidayOverride>,
+        override_warning: Option<String>,
+    ) -> Self {
+        Self {
             id,
+            campus,
+            cache,
+            http,
+            offline: false,
             ttr: None,
-            cr: None,
+            catalog: None,
             holiday_response: None,
+            holiday_source: None,
+            holiday_override,
+            override_warning,
             timetable: None,
-        };
-
-        client.fetch_courses()?;
-        client.fetch_holidays()?;
-        // client.fetch_timetable()?;
-        // client.update_time_table();
-        Ok(client)
+        }
     }
-    // https://raw.githubusercontent.com/lokesh185/chrono-to-ics-prototype/master/holidays.json
     pub fn update_time_table(&mut self) -> Option<()> {
         self.timetable = TimeTable::new(
             self.ttr.as_ref()?,
-            self.cr.as_ref()?,
+            self.catalog.as_ref()?,
             self.holiday_response.as_ref()?,
-        );
+            self.holiday_override.as_ref(),
+            self.campus,
+        )
+        .map(|mut time_table| {
+            time_table.warnings.extend(self.override_warning.clone());
+            time_table
+        });
         Some(())
     }
-    fn fetch_holidays(&mut self) -> Result<(), Error> {
-        let client = Client::new();
-        let cresponse = client
-            .get("https://raw.githubusercontent.com/lokesh185/chrono-to-ics-prototype/master/holidays.json")
-            .send()?;
-        self.holiday_response = Some(cresponse.error_for_status()?.json::<HolidayResponse>()?);
+    pub fn campus(&self) -> Campus {
+        self.campus
+    }
+    /// Switches campus, refetching the holiday data of the new one.
+    pub fn set_campus(&mut self, campus: Campus) -> Result<(), ApiError> {
+        if self.campus != campus {
+            self.campus = campus;
+            self.fetch_holidays()?;
+        }
         Ok(())
     }
-    fn fetch_courses(&mut self) -> Result<(), Error> {
-        let client = Client::new();
-        let cresponse = client
-            .get("https://www.chrono.crux-bphc.com/api/course")
-            .send()?;
-        self.cr = Some(cresponse.error_for_status()?.json::<CourseResponse>()?);
+    /// Holiday data of the fetched timetable's semester (or the latest one before a
+    /// timetable is fetched) as downloaded, without the local override applied.
+    pub fn holidays(&self) -> Option<&HolidayResponse> {
+        let academic_calendar = self.holiday_response.as_ref()?;
+        self.ttr
+            .as_ref()
+            .and_then(|ttr| academic_calendar.for_time_table(ttr))
+            .or_else(|| academic_calendar.latest())
+    }
+    pub fn academic_calendar(&self) -> Option<&AcademicCalendarResponse> {
+        self.holiday_response.as_ref()
+    }
+    /// The fetched responses, to be saved for offline use.
+    pub fn snapshot(&self) -> Option<Snapshot> {
+        Some(Snapshot::new(
+            self.campus,
+            self.ttr.as_ref()?,
+            self.catalog.as_ref()?,
+            self.holiday_response.as_ref()?,
+        ))
+    }
+    /// The indexed course catalog, cheap to clone and share with other clients.
+    pub fn catalog(&self) -> Option<Arc<CourseCatalog>> {
+        self.catalog.clone()
+    }
+    /// Whether some of the data is a cached copy used because the server was unreachable.
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+    pub fn holiday_source(&self) -> Option<HolidaySource> {
+        self.holiday_source
+    }
+    /// The http client, to share its connections with other clients.
+    pub fn http(&self) -> &Http {
+        &self.http
+    }
+    /// Prefers the remote holiday data, falling back to the bundled copy
+    /// when it can't be fetched or is older than what shipped with the app.
+    fn fetch_holidays(&mut self) -> Result<(), ApiError> {
+        let remote = match self.campus.profile().holidays_url {
+            Some(url) => {
+                get::<AcademicCalendarResponse>(&self.cache, &self.http, &url, self.cache.ttl)
+            }
+            None => Err(ApiError::NoHolidayData(self.campus)),
+        };
+        self.set_holidays(remote)
+    }
+    fn set_holidays(
+        &mut self,
+        remote: Result<(AcademicCalendarResponse, bool), ApiError>,
+    ) -> Result<(), ApiError> {
+        let (holiday_response, source, stale) = pick_holidays(self.campus, remote)?;
+        self.holiday_response = Some(holiday_response);
+        self.holiday_source = Some(source);
+        self.offline |= stale;
         Ok(())
     }
-    pub fn fetch_timetable(&mut self) -> Result<(), Error> {
-        let client = Client::new();
-        let api_link = format!(
-            "https://www.chrono.crux-bphc.com/api/timetable/{}",
-            &self.id
-        );
-        let response = client.get(api_link).send()?;
-
-        self.ttr = Some(response.error_for_status()?.json::<TimeTableResponse>()?);
+    /// Fetches the timetable and holiday data again, the catalog only once the cache ttl
+    /// is over. Holiday data given to [`Self::with_data`] is kept.
+    pub fn refresh(&mut self) -> Result<(), ApiError> {
+        let holidays = self.holiday_source != Some(HolidaySource::Local);
+        self.offline = false;
+        self.fetch(true, holidays, true)
+    }
+    pub fn fetch_timetable(&mut self) -> Result<(), ApiError> {
+        let (ttr, stale) = get::<TimeTableResponse>(
+            &self.cache,
+            &self.http,
+            &self.timetable_url(),
+            Duration::ZERO,
+        )?;
+        self.ttr = Some(ttr);
+        self.offline |= stale;
         Ok(())
     }
+    fn timetable_url(&self) -> String {
+        self.http.options().timetable_url(&self.id)
+    }
+    /// Fetches the catalog, holidays and timetable asked for, each on its own thread over
+    /// the shared client.
+    fn fetch(&mut self, courses: bool, holidays: bool, timetable: bool) -> Result<(), ApiError> {
+        let (campus, holidays_url) = (self.campus, self.campus.profile().holidays_url);
+        let course_url = self.http.options().course_url();
+        let timetable_url = timetable.then(|| self.timetable_url());
+        let (cache, http) = (&self.cache, &self.http);
+        let (course_response, holiday_response, time_table_response) = thread::scope(|scope| {
+            let course_response = courses.then(|| {
+                scope.spawn(move || get::<CourseResponse>(cache, http, &course_url, cache.ttl))
+            });
+            // timetables get edited, so always ask whether the cached one is still current
+            let time_table_response = timetable_url.as_deref().map(|url| {
+                scope.spawn(move || get::<TimeTableResponse>(cache, http, url, Duration::ZERO))
+            });
+            let holiday_response = holidays.then(|| match &holidays_url {
+                Some(url) => get::<AcademicCalendarResponse>(cache, http, url, cache.ttl),
+                None => Err(ApiError::NoHolidayData(campus)),
+            });
+            (
+                course_response.map(join),
+                holiday_response,
+                time_table_response.map(join),
+            )
+        });
+        if let Some(course_response) = course_response {
+            let (course_response, stale) = course_response?;
+            self.catalog = Some(Arc::new(CourseCatalog::new(course_response)));
+            self.offline |= stale;
+        }
+        if let Some(holiday_response) = holiday_response {
+            self.set_holidays(holiday_response)?;
+        }
+        if let Some(time_table_response) = time_table_response {
+            let (ttr, stale) = time_table_response?;
+            self.ttr = Some(ttr);
+            self.offline |= stale;
+        }
+        Ok(())
+    }
+}
+
+fn join<T>(handle: thread::ScopedJoinHandle<'_, T>) -> T {
+    handle
+        .join()
+        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+}
+/// Body of `url` through the cache, and whether it is a stale copy used while offline.
+fn get<T: DeserializeOwned>(
+    cache: &Cache,
+    http: &Http,
+    url: &str,
+    max_age: Duration,
+) -> Result<(T, bool), ApiError> {
+    let (body, status) = cache.get(http, url, max_age)?;
+    parse(&body, status)
 }
@@ -1,16 +1,25 @@
-use super::responses::{CourseResponse, HolidayResponse, SectionResponse, TimeTableResponse};
-use chrono::{DateTime, Utc, Weekday};
+use super::catalog::CourseCatalog;
+use super::holidays::{acad_year_label, HolidayOverride};
+use super::responses::{
+    AcademicCalendarResponse, HolidayString, SectionResponse, TimeTableResponse,
+};
+use super::validate::{validate, Severity};
+use crate::campus::Campus;
+use crate::schedule::{self, Occurrence};
+use chrono::{DateTime, Days, NaiveDate, Utc, Weekday};
 use regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::{fmt, str::FromStr, vec};
 #[derive(Debug, Clone)]
-struct DayError;
+pub(crate) struct DayError;
 impl fmt::Display for DayError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "invalid string to parse to Weekday")
     }
 }
 #[derive(Debug)]
-struct WeekdayWrapper(Weekday);
+pub(crate) struct WeekdayWrapper(Weekday);
 impl WeekdayWrapper {
     pub fn consume_to_weekday(self) -> Weekday {
         self.0
@@ -31,7 +40,7 @@ impl FromStr for WeekdayWrapper {
         }))
     }
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Timing {
     pub day: Weekday,
     pub classroom: String,
@@ -43,9 +52,7 @@ impl Timing {
     pub fn from_string(info: &str) -> Option<Self> {
         let re = regex::Regex::new(r"\w+ \w\d{3}:(\w\d{3}):(\w+):(\d+)").ok()?;
 
-        let Some(caps) = re.captures(info) else {
-            return None;
-        };
+        let caps = re.captures(info)?;
         let classroom = caps.get(1)?.as_str().to_string();
         let day = caps
             .get(2)?
@@ -62,9 +69,10 @@ impl Timing {
         })
     }
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Section {
     pub number: i32,
+    #[serde(default)]
     pub instructors: Vec<String>,
     pub timings: Vec<Timing>,
 }
@@ -90,35 +98,67 @@ impl Section {
         self.timings = new_timings;
     }
 }
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Course {
-    id: String,
+    /// id in the chrono catalog
+    #[serde(default)]
+    pub(crate) id: String,
     pub code: String,
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub lecture: Option<Section>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub tutorial: Option<Section>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub lab: Option<Section>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub midsem_date_time: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub compre_date_time: Option<(DateTime<Utc>, DateTime<Utc>)>,
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SectionKind {
+    Lecture,
+    Tutorial,
+    Lab,
+}
+impl fmt::Display for SectionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Lecture => write!(f, "lecture"),
+            Self::Tutorial => write!(f, "tutorial"),
+            Self::Lab => write!(f, "lab"),
+        }
+    }
+}
 impl Course {
-    fn new(id: String, course_response: &CourseResponse) -> Option<Self> {
-        course_response
-            .courses
-            .iter()
-            .find_map(|course| match course.id.eq(&id) {
-                true => Some(Self {
-                    id: id.clone(),
-                    code: course.code.clone(),
-                    name: course.name.clone(),
-                    lecture: None,
-                    tutorial: None,
-                    lab: None,
-                    midsem_date_time: None,
-                    compre_date_time: None,
-                }),
-                false => None,
-            })
+    /// Id of the course in the chrono catalog.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    /// The sections the timetable has for this course, lecture first.
+    pub fn sections(&self) -> impl Iterator<Item = (SectionKind, &Section)> {
+        [
+            (SectionKind::Lecture, &self.lecture),
+            (SectionKind::Tutorial, &self.tutorial),
+            (SectionKind::Lab, &self.lab),
+        ]
+        .into_iter()
+        .filter_map(|(kind, section)| Some((kind, section.as_ref()?)))
+    }
+    fn new(id: String, catalog: &CourseCatalog) -> Option<Self> {
+        let course = catalog.get(&id)?;
+        Some(Self {
+            code: course.code.clone(),
+            name: course.name.clone(),
+            id,
+            lecture: None,
+            tutorial: None,
+            lab: None,
+            midsem_date_time: None,
+            compre_date_time: None,
+        })
     }
     fn add_section(&mut self, section_response: &SectionResponse) {
         let timings = section_response
@@ -155,53 +195,265 @@ impl Course {
             });
     }
 }
-#[derive(Debug, Default)]
+#[derive(Debug, Clone)]
+pub struct HolidayError(String);
+impl fmt::Display for HolidayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HolidayKind {
+    /// national holidays, everyone gets them
+    Gazetted,
+    /// optional holidays, only cancel classes for those who take them
+    Restricted,
+    #[default]
+    Institute,
+}
+impl FromStr for HolidayKind {
+    type Err = HolidayError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "gazetted" => Ok(Self::Gazetted),
+            "restricted" => Ok(Self::Restricted),
+            "institute" => Ok(Self::Institute),
+            _ => Err(HolidayError(format!(
+                "invalid holiday kind \"{}\", expected gazetted, restricted or institute",
+                s
+            ))),
+        }
+    }
+}
+/// Which classes of the day a holiday cancels.
+/// Written the way holidays.json has it: full, after:<slot> or before:<slot>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Closure {
+    #[default]
+    FullDay,
+    /// classes starting before the slot are cancelled
+    BeforeSlot(u8),
+    /// classes ending after the slot are cancelled
+    AfterSlot(u8),
+}
+impl Closure {
+    // half days split at noon, slots 1-4 are the morning
+    const MORNING: Self = Self::BeforeSlot(5);
+    const AFTERNOON: Self = Self::AfterSlot(4);
+    pub fn cancels(&self, start: u8, end: u8) -> bool {
+        match self {
+            Self::FullDay => true,
+            Self::BeforeSlot(slot) => start < *slot,
+            Self::AfterSlot(slot) => end > *slot,
+        }
+    }
+}
+impl FromStr for Closure {
+    type Err = HolidayError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let slot = |slot: &str| slot.trim().parse::<u8>().ok();
+        match s.split_once(':') {
+            None if s == "full" => Some(Self::FullDay),
+            None if s == "morning" => Some(Self::MORNING),
+            None if s == "afternoon" => Some(Self::AFTERNOON),
+            Some(("before", n)) => slot(n).map(Self::BeforeSlot),
+            Some(("after", n)) => slot(n).map(Self::AfterSlot),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            HolidayError(format!(
+                "invalid closure \"{}\", expected full, morning, afternoon, after:<slot> or before:<slot>",
+                s
+            ))
+        })
+    }
+}
+impl fmt::Display for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::FullDay => write!(f, "full"),
+            Self::BeforeSlot(slot) => write!(f, "before:{}", slot),
+            Self::AfterSlot(slot) => write!(f, "after:{}", slot),
+        }
+    }
+}
+impl From<Closure> for String {
+    fn from(closure: Closure) -> Self {
+        closure.to_string()
+    }
+}
+impl TryFrom<String> for Closure {
+    type Error = HolidayError;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse::<Closure>()
+    }
+}
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Holiday {
     pub name: String,
     pub date: DateTime<Utc>,
+    #[serde(default)]
+    pub kind: HolidayKind,
+    #[serde(default)]
+    pub closure: Closure,
 }
-#[derive(Debug)]
-struct TimeTableChange {
-    day: Weekday,
-    date: DateTime<Utc>,
+impl Holiday {
+    /// Parses a holiday entry, names still marked the old way with an "(H)" or "(R)"
+    /// suffix get the matching kind and lose the suffix.
+    pub fn from_response(holiday_string: &HolidayString) -> Result<Self, HolidayError> {
+        match Self::from_response_lenient(holiday_string)? {
+            (holiday, problems) if problems.is_empty() => Ok(holiday),
+            (_, mut problems) => Err(problems.remove(0)),
+        }
+    }
+    /// [`Holiday::from_response`] that keeps the day off when the kind or closure is unknown:
+    /// it becomes an institute holiday closing the whole day, and the problems are returned
+    /// with it. Only an invalid date fails.
+    pub fn from_response_lenient(
+        holiday_string: &HolidayString,
+    ) -> Result<(Self, Vec<HolidayError>), HolidayError> {
+        let mut problems: Vec<HolidayError> = vec![];
+        let name = holiday_string.name.trim();
+        let (name, suffix_kind) = if let Some(name) = name.strip_suffix("(H)") {
+            (name.trim_end(), Some(HolidayKind::Gazetted))
+        } else if let Some(name) = name.strip_suffix("(R)") {
+            (name.trim_end(), Some(HolidayKind::Restricted))
+        } else {
+            (name, None)
+        };
+        let holiday = Self {
+            name: name.to_string(),
+            date: holiday_string
+                .date
+                .parse::<DateTime<Utc>>()
+                .map_err(|_| HolidayError(format!("invalid date \"{}\"", holiday_string.date)))?,
+            kind: match &holiday_string.kind {
+                Some(kind) => kind.parse::<HolidayKind>().unwrap_or_else(|e| {
+                    problems.push(e);
+                    HolidayKind::Institute
+                }),
+                None => suffix_kind.unwrap_or_default(),
+            },
+            closure: match &holiday_string.closure {
+                Some(closure) => closure.parse::<Closure>().unwrap_or_else(|e| {
+                    problems.push(e);
+                    Closure::FullDay
+                }),
+                None => Closure::FullDay,
+            },
+        };
+        Ok((holiday, problems))
+    }
 }
-#[derive(Debug)]
+/// A date that follows another weekday's timetable.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeTableChange {
+    pub day: Weekday,
+    pub date: DateTime<Utc>,
+}
+/// The linked timetable. Serializes to the json described in the readme, see
+/// [`TimeTable::to_json`] for the versioned form.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TimeTable {
+    #[serde(default)]
     pub id: String,
+    #[serde(default)]
     pub name: String,
+    #[serde(default)]
     pub acad_year: i32,
+    #[serde(default)]
+    pub campus: Campus,
     pub classwork_start: DateTime<Utc>,
     pub classwork_end: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub midsem_dates: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    #[serde(default)]
     pub courses: Vec<Course>,
+    #[serde(default)]
     pub holidays: Vec<Holiday>,
-    time_table_changes: Vec<TimeTableChange>,
+    /// problems found while linking the responses, e.g. holiday data for another semester
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    #[serde(default)]
+    pub time_table_changes: Vec<TimeTableChange>,
 }
 impl TimeTable {
     pub fn new(
         time_table_response: &TimeTableResponse,
-        course_response: &CourseResponse,
-        holiday_response: &HolidayResponse,
+        catalog: &CourseCatalog,
+        academic_calendar: &AcademicCalendarResponse,
+        holiday_override: Option<&HolidayOverride>,
+        campus: Campus,
     ) -> Option<Self> {
+        let mut warnings: Vec<String> = vec![];
+        let acad_year = acad_year_label(time_table_response.acadYear);
+        let wanted = match time_table_response.semester {
+            Some(semester) => format!("semester {} of {}", semester, acad_year),
+            None => acad_year.clone(),
+        };
+        let holiday_response = match academic_calendar.for_time_table(time_table_response) {
+            Some(holiday_response) => {
+                if time_table_response.semester.is_none() {
+                    warnings.push(format!(
+                        "the timetable doesn't say which semester it is for, using semester {} of {}",
+                        holiday_response.semester, acad_year,
+                    ));
+                }
+                holiday_response
+            }
+            None => {
+                let latest = academic_calendar.latest()?;
+                warnings.push(format!(
+                    "no holiday data for {}, using semester {} of {} instead",
+                    wanted,
+                    latest.semester,
+                    acad_year_label(latest.acad_year),
+                ));
+                latest
+            }
+        };
+        let merged_holidays;
+        let holiday_response = match holiday_override {
+            Some(holiday_override) if holiday_override.applies_to(holiday_response) => {
+                merged_holidays = holiday_override.apply(holiday_response);
+                &merged_holidays
+            }
+            Some(_) => {
+                warnings.push(
+                    "local holiday override is for a different semester, ignoring it".to_string(),
+                );
+                holiday_response
+            }
+            None => holiday_response,
+        };
+        if holiday_response.holidays.is_empty() {
+            warnings.push(format!(
+                "no holidays are listed for semester {} of {} on {}, classes are kept on every day",
+                holiday_response.semester,
+                acad_year_label(holiday_response.acad_year),
+                campus,
+            ));
+        }
         let mut courses: Vec<Course> = vec![];
+        // position in `courses` of every course seen so far
+        let mut course_index: HashMap<&str, usize> = HashMap::new();
         time_table_response
             .sections
             .iter()
             .for_each(|section_response| {
-                match courses.iter_mut().find_map(|course| {
-                    match course.id.eq(&section_response.courseId) {
-                        true => Some(course),
-                        false => None,
-                    }
-                }) {
-                    Some(course) => {
-                        course.add_section(section_response);
+                match course_index.get(section_response.courseId.as_str()) {
+                    Some(&i) => {
+                        courses[i].add_section(section_response);
                     }
                     None => {
                         if let Some(mut course) =
-                            Course::new(section_response.courseId.clone(), course_response)
+                            Course::new(section_response.courseId.clone(), catalog)
                         {
                             course.add_section(section_response);
+                            course_index.insert(&section_response.courseId, courses.len());
                             courses.push(course)
                         }
                     }
@@ -212,24 +464,32 @@ impl TimeTable {
             .iter()
             .filter_map(|info| ExamTime::from_string(info.clone()).ok())
             .collect::<Vec<ExamTime>>();
-        dbg!(&courses);
 
         courses
             .iter_mut()
             .for_each(|course| course.update_exam_time(&exam_times));
-        dbg!(&courses);
 
+        // entries with these problems are skipped below, so make them visible
+        warnings.extend(
+            validate(holiday_response)
+                .into_iter()
+                .filter(|issue| issue.severity == Severity::Error)
+                .map(|issue| format!("holiday data: {}", issue.message)),
+        );
         let holidays = holiday_response
             .holidays
             .iter()
-            .filter_map(|holiday_string| {
-                Some(Holiday {
-                    name: holiday_string.name.clone(),
-                    date: holiday_string.date.as_str().parse::<DateTime<Utc>>().ok()?,
-                })
+            .filter_map(|holiday_string| Holiday::from_response_lenient(holiday_string).ok())
+            .map(|(holiday, problems)| {
+                warnings.extend(problems.iter().map(|e| {
+                    format!(
+                        "holiday \"{}\": {}, kept as a full day institute holiday",
+                        holiday.name, e
+                    )
+                }));
+                holiday
             })
             .collect::<Vec<Holiday>>();
-        dbg!(&holidays);
         let time_table_changes = holiday_response
             .time_table_changes
             .iter()
@@ -248,8 +508,10 @@ impl TimeTable {
             id: time_table_response.id.clone(),
             name: time_table_response.name.clone(),
             acad_year: time_table_response.acadYear,
+            campus,
             courses,
             holidays,
+            warnings,
             time_table_changes,
             midsem_dates: if let (Ok(midsem_st), Ok(midsem_end)) = (
                 holiday_response.midsem_start.parse::<DateTime<Utc>>(),
@@ -269,11 +531,58 @@ impl TimeTable {
                 .ok()?,
         })
     }
+    /// Every class between `from` and `to` (inclusive, campus-local dates) with holidays,
+    /// midsem and day swaps applied.
+    pub fn occurrences(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> impl Iterator<Item = Occurrence<'_>> {
+        schedule::occurrences(self, from, to)
+    }
+    /// Classes on a campus-local date, in order.
+    pub fn day(&self, date: NaiveDate) -> Vec<Occurrence<'_>> {
+        self.occurrences(date, date).collect()
+    }
+    /// Classes from the monday to the sunday of the week `date` is in.
+    pub fn week(&self, date: NaiveDate) -> Vec<Occurrence<'_>> {
+        let monday = date.week(Weekday::Mon).first_day();
+        let sunday = monday
+            .checked_add_days(Days::new(6))
+            .unwrap_or(NaiveDate::MAX);
+        self.occurrences(monday, sunday).collect()
+    }
+    /// The first class starting after `now`, possibly on a later day.
+    pub fn next_class(&self, now: DateTime<Utc>) -> Option<Occurrence<'_>> {
+        let today = self.campus.profile().local_date(&now);
+        self.occurrences(today, NaiveDate::MAX)
+            .find(|occurrence| occurrence.start > now)
+    }
+    /// Drops the restricted holidays the user does not take, so classes on those days stay.
+    /// Names are matched case-insensitively.
+    pub fn keep_restricted_holidays(&mut self, taken: &[String]) {
+        self.holidays.retain(|holiday| {
+            holiday.kind != HolidayKind::Restricted
+                || taken
+                    .iter()
+                    .any(|name| name.trim().eq_ignore_ascii_case(&holiday.name))
+        });
+    }
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ExamKind {
     Midsem,
     Compre,
 }
+impl fmt::Display for ExamKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Midsem => write!(f, "midsem"),
+            Self::Compre => write!(f, "compre"),
+        }
+    }
+}
 pub struct ExamTime {
     code: String,
     exam_type: ExamKind,
@@ -339,3 +648,68 @@ impl ExamTime {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holiday(kind: Option<&str>, closure: Option<&str>) -> HolidayString {
+        HolidayString {
+            name: "Diwali (H)".to_string(),
+            date: "2024-10-31T00:00:00+05:30".to_string(),
+            kind: kind.map(str::to_string),
+            closure: closure.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parses_kind_and_closure() {
+        let holiday = Holiday::from_response(&holiday(None, Some("afternoon"))).unwrap();
+        assert_eq!(holiday.name, "Diwali");
+        assert_eq!(holiday.kind, HolidayKind::Gazetted);
+        assert_eq!(holiday.closure, Closure::AfterSlot(4));
+    }
+
+    #[test]
+    fn unknown_kind_and_closure_keep_the_day_off() {
+        let entry = holiday(Some("national"), Some("evening"));
+        assert!(Holiday::from_response(&entry).is_err());
+        let (holiday, problems) = Holiday::from_response_lenient(&entry).unwrap();
+        assert_eq!(holiday.kind, HolidayKind::Institute);
+        assert_eq!(holiday.closure, Closure::FullDay);
+        assert_eq!(problems.len(), 2);
+    }
+
+    #[test]
+    fn invalid_date_fails() {
+        let entry = HolidayString {
+            date: "31/10/2024".to_string(),
+            ..holiday(None, None)
+        };
+        assert!(Holiday::from_response_lenient(&entry).is_err());
+    }
+
+    #[test]
+    fn links_with_bundled_holidays() {
+        let response = TimeTableResponse {
+            acadYear: 2024,
+            semester: Some(2),
+            ..Default::default()
+        };
+        let catalog = CourseCatalog::new(Default::default());
+        for (campus, calendar) in Campus::ALL
+            .into_iter()
+            .filter_map(|campus| Some((campus, crate::api::holidays::bundled(campus)?)))
+        {
+            let time_table = TimeTable::new(&response, &catalog, &calendar, None, campus).unwrap();
+            assert_eq!(time_table.campus, campus);
+            assert_eq!(
+                time_table.holidays.is_empty(),
+                time_table
+                    .warnings
+                    .iter()
+                    .any(|warning| warning.starts_with("no holidays are listed")),
+            );
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use super::responses::{
+    AcademicCalendarResponse, HolidayResponse, HolidayString, TimeTableChangeResponse,
+    TimeTableResponse,
+};
+use crate::campus::{Campus, CampusProfile};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const OVERRIDE_FILE: &str = "holidays_override.json";
+
+/// Local changes made on top of the remote holiday data.
+/// Only the differences are stored so that later updates of the remote file still apply.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct HolidayOverride {
+    /// semester the override was made for, 0 applies to any semester
+    #[serde(default)]
+    pub acad_year: i32,
+    #[serde(default)]
+    pub semester: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classwork_start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classwork_end: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub midsem_start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub midsem_end: Option<String>,
+    #[serde(default)]
+    pub holidays: Vec<HolidayString>,
+    #[serde(default)]
+    pub removed_holidays: Vec<HolidayString>,
+    #[serde(default)]
+    pub time_table_changes: Vec<TimeTableChangeResponse>,
+    #[serde(default)]
+    pub removed_time_table_changes: Vec<TimeTableChangeResponse>,
+}
+impl HolidayOverride {
+    /// Builds the override that turns `remote` into `edited`.
+    pub fn between(remote: &HolidayResponse, edited: &HolidayResponse) -> Self {
+        let changed = |remote: &String, edited: &String| match remote.eq(edited) {
+            true => None,
+            false => Some(edited.clone()),
+        };
+        Self {
+            acad_year: remote.acad_year,
+            semester: remote.semester,
+            classwork_start: changed(&remote.classwork_start, &edited.classwork_start),
+            classwork_end: changed(&remote.classwork_end, &edited.classwork_end),
+            midsem_start: changed(&remote.midsem_start, &edited.midsem_start),
+            midsem_end: changed(&remote.midsem_end, &edited.midsem_end),
+            holidays: missing_from(&edited.holidays, &remote.holidays),
+            removed_holidays: missing_from(&remote.holidays, &edited.holidays),
+            time_table_changes: missing_from(
+                &edited.time_table_changes,
+                &remote.time_table_changes,
+            ),
+            removed_time_table_changes: missing_from(
+                &remote.time_table_changes,
+                &edited.time_table_changes,
+            ),
+        }
+    }
+    /// Returns `remote` with this override merged over it.
+    pub fn apply(&self, remote: &HolidayResponse) -> HolidayResponse {
+        let pick =
+            |local: &Option<String>, remote: &String| local.as_ref().unwrap_or(remote).clone();
+        let mut holidays = missing_from(&remote.holidays, &self.removed_holidays);
+        holidays.extend(missing_from(&self.holidays, &holidays));
+        let mut time_table_changes =
+            missing_from(&remote.time_table_changes, &self.removed_time_table_changes);
+        time_table_changes.extend(missing_from(&self.time_table_changes, &time_table_changes));
+        HolidayResponse {
+            acad_year: remote.acad_year,
+            semester: remote.semester,
+            classwork_start: pick(&self.classwork_start, &remote.classwork_start),
+            classwork_end: pick(&self.classwork_end, &remote.classwork_end),
+            midsem_start: pick(&self.midsem_start, &remote.midsem_start),
+            midsem_end: pick(&self.midsem_end, &remote.midsem_end),
+            holidays,
+            time_table_changes,
+        }
+    }
+    pub fn applies_to(&self, semester: &HolidayResponse) -> bool {
+        (self.acad_year == 0 || self.acad_year == semester.acad_year)
+            && (self.semester == 0 || self.semester == semester.semester)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.eq(&Self::default())
+    }
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        serde_json::from_str(&data).map_err(io::Error::from)
+    }
+    /// [`HolidayOverride::load`], `None` when there is no file.
+    pub fn load_if_present(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        match Self::load(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            result => result.map(Some),
+        }
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Where the holiday editor saves the override, in the data dir.
+#[cfg(feature = "http")]
+pub fn override_path() -> PathBuf {
+    super::cache::data_dir().join(OVERRIDE_FILE)
+}
+/// The saved override, and a warning when there is a file that can't be used.
+#[cfg(feature = "http")]
+pub fn saved_override() -> (Option<HolidayOverride>, Option<String>) {
+    let path = override_path();
+    match HolidayOverride::load_if_present(&path) {
+        Ok(holiday_override) => (holiday_override, None),
+        Err(e) => (
+            None,
+            Some(format!(
+                "ignoring holiday override {}: {}",
+                path.display(),
+                e
+            )),
+        ),
+    }
+}
+
+fn missing_from<T: Clone + PartialEq>(items: &[T], other: &[T]) -> Vec<T> {
+    items
+        .iter()
+        .filter(|item| !other.contains(item))
+        .cloned()
+        .collect::<Vec<T>>()
+}
+
+/// Accepts either a full rfc3339 date time or a plain `YYYY-MM-DD` date,
+/// which is stored as midnight campus time like the rest of holidays.json.
+pub fn normalize_date(input: &str, profile: &CampusProfile) -> Option<String> {
+    let input = input.trim();
+    if input.parse::<DateTime<FixedOffset>>().is_ok() {
+        return Some(input.to_string());
+    }
+    date_string(NaiveDate::parse_from_str(input, "%Y-%m-%d").ok()?, profile)
+}
+
+/// Formats a date the way holidays.json stores it, midnight campus time in rfc3339.
+pub fn date_string(date: NaiveDate, profile: &CampusProfile) -> Option<String> {
+    let midnight = profile
+        .to_utc(date, NaiveTime::MIN)?
+        .with_timezone(&profile.timezone);
+    Some(midnight.to_rfc3339())
+}
+
+impl AcademicCalendarResponse {
+    pub fn find(&self, acad_year: i32, semester: u8) -> Option<&HolidayResponse> {
+        self.semesters
+            .iter()
+            .find(|holidays| holidays.acad_year == acad_year && holidays.semester == semester)
+    }
+    /// The semester a timetable is for, the latest one of its academic year when the
+    /// timetable doesn't say.
+    pub fn for_time_table(&self, time_table: &TimeTableResponse) -> Option<&HolidayResponse> {
+        match time_table.semester {
+            Some(semester) => self.find(time_table.acadYear, semester),
+            None => self
+                .semesters
+                .iter()
+                .filter(|holidays| holidays.acad_year == time_table.acadYear)
+                .max_by_key(|holidays| holidays.semester),
+        }
+    }
+    /// Replaces the semester with the same academic year and number, or adds it.
+    pub fn upsert(&mut self, holidays: HolidayResponse) {
+        match self.semesters.iter_mut().find(|semester| {
+            semester.acad_year == holidays.acad_year && semester.semester == holidays.semester
+        }) {
+            Some(semester) => *semester = holidays,
+            None => self.semesters.push(holidays),
+        }
+    }
+    pub fn latest(&self) -> Option<&HolidayResponse> {
+        self.semesters
+            .iter()
+            .max_by_key(|holidays| (holidays.acad_year, holidays.semester))
+    }
+}
+
+/// Formats an academic year the way the institute does, 2024 -> "2024-25".
+pub fn acad_year_label(acad_year: i32) -> String {
+    format!("{}-{:02}", acad_year, (acad_year + 1) % 100)
+}
+
+/// The holiday data shipped inside the binary, used when the remote copy is unreachable or older.
+pub fn bundled(campus: Campus) -> Option<AcademicCalendarResponse> {
+    let data = campus.profile().bundled_holidays?;
+    Some(serde_json::from_str(data).expect("bundled holidays.json is valid"))
+}
+
+/// Where holiday data should come from.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum HolidayData {
+    /// downloaded, falling back to the bundled copy
+    #[default]
+    Remote,
+    /// only the copy bundled with the app
+    Bundled,
+    /// a holidays.json on disk
+    File(PathBuf),
+}
+impl HolidayData {
+    /// The holiday data when it doesn't have to be downloaded.
+    pub fn load_local(&self, campus: Campus) -> Option<io::Result<AcademicCalendarResponse>> {
+        match self {
+            Self::Remote => None,
+            Self::Bundled => Some(bundled(campus).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no holiday data is bundled for {}", campus),
+                )
+            })),
+            Self::File(path) => Some(
+                fs::read_to_string(path)
+                    .and_then(|data| serde_json::from_str(&data).map_err(io::Error::from)),
+            ),
+        }
+    }
+}
+
+/// Which copy of the holiday data ended up being used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HolidaySource {
+    Remote,
+    /// chosen with [`HolidayData::Bundled`] or [`HolidayData::File`]
+    Local,
+    /// the remote copy could not be fetched
+    Bundled,
+    /// the remote copy has an older version than the bundled one
+    BundledNewer,
+}
+impl fmt::Display for HolidaySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Remote => write!(f, "using downloaded holiday data"),
+            Self::Local => write!(f, "using local holiday data"),
+            Self::Bundled => write!(
+                f,
+                "unable to download holiday data, using the copy bundled with the app"
+            ),
+            Self::BundledNewer => write!(
+                f,
+                "downloaded holiday data is outdated, using the copy bundled with the app"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data::Holiday;
+    use crate::api::validate::{validate_calendar, Severity};
+
+    #[test]
+    fn bundled_files_parse() {
+        for (campus, data) in Campus::ALL
+            .into_iter()
+            .filter_map(|campus| Some((campus, campus.profile().bundled_holidays?)))
+        {
+            let calendar: AcademicCalendarResponse = serde_json::from_str(data)
+                .unwrap_or_else(|e| panic!("holidays for {}: {}", campus, e));
+            assert!(!calendar.semesters.is_empty(), "{}", campus);
+            let errors = validate_calendar(&calendar)
+                .into_iter()
+                .filter(|issue| issue.severity == Severity::Error)
+                .map(|issue| issue.message)
+                .collect::<Vec<_>>();
+            assert!(errors.is_empty(), "holidays for {}: {:?}", campus, errors);
+            for semester in &calendar.semesters {
+                for holiday in &semester.holidays {
+                    Holiday::from_response(holiday)
+                        .unwrap_or_else(|e| panic!("{} on {}: {}", holiday.name, campus, e));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use super::cache::CacheStatus;
+use super::holidays::{self, HolidaySource};
+use super::responses::AcademicCalendarResponse;
+use crate::campus::Campus;
+#[cfg(feature = "http-blocking")]
+use reqwest::blocking;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use std::fmt;
+#[cfg(feature = "http-blocking")]
+use std::thread;
+use std::time::Duration;
+
+/// A response that could not be fetched or did not parse.
+#[derive(Debug)]
+pub enum ApiError {
+    Network(reqwest::Error),
+    InvalidData(serde_json::Error),
+    /// the campus has no holiday data, neither online nor bundled
+    NoHolidayData(Campus),
+}
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Network(e) => write!(f, "{}", e),
+            Self::InvalidData(e) => write!(f, "unexpected response: {}", e),
+            Self::NoHolidayData(campus) => write!(
+                f,
+                "no holiday data for {}, a holidays.json has to be given",
+                campus
+            ),
+        }
+    }
+}
+impl std::error::Error for ApiError {}
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Network(e)
+    }
+}
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::InvalidData(e)
+    }
+}
+
+pub const CHRONO_API_URL: &str = "https://www.chrono.crux-bphc.com/api";
+
+/// Timeouts and retries of the requests made to the chrono and holiday servers.
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    /// whole request, including reading the body
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// extra attempts after a timeout, a refused connection or a 5xx/429 response
+    pub retries: u32,
+    /// wait before the first retry, doubled on every following one
+    pub backoff: Duration,
+    pub user_agent: String,
+    /// chrono api the catalog and timetables are fetched from, [`CHRONO_API_URL`] unless
+    /// pointed at a local stand-in
+    pub api_url: String,
+}
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            retries: 2,
+            backoff: Duration::from_millis(500),
+            user_agent: format!(
+                "{}/{} (+{})",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"),
+                "https://github.com/lokesh185/chrono-to-ics"
+            ),
+            api_url: CHRONO_API_URL.to_string(),
+        }
+    }
+}
+impl HttpOptions {
+    pub(crate) fn course_url(&self) -> String {
+        format!("{}/course", self.api_url.trim_end_matches('/'))
+    }
+    pub(crate) fn timetable_url(&self, id: &str) -> String {
+        format!("{}/timetable/{}", self.api_url.trim_end_matches('/'), id)
+    }
+    fn backoff_before(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+/// One http client shared by every request, so connections and tls sessions are reused.
+/// Cloning is cheap and shares the connection pool.
+#[cfg(feature = "http-blocking")]
+#[derive(Debug, Clone)]
+pub struct Http {
+    client: blocking::Client,
+    options: HttpOptions,
+}
+#[cfg(feature = "http-blocking")]
+impl Http {
+    pub fn new(options: HttpOptions) -> Result<Self, reqwest::Error> {
+        let client = blocking::Client::builder()
+            .timeout(options.timeout)
+            .connect_timeout(options.connect_timeout)
+            .user_agent(options.user_agent.as_str())
+            .build()?;
+        Ok(Self { client, options })
+    }
+    pub fn options(&self) -> &HttpOptions {
+        &self.options
+    }
+    pub fn get(&self, url: &str) -> blocking::RequestBuilder {
+        self.client.get(url)
+    }
+    /// Any other method, e.g. PUT or the webdav ones.
+    pub fn request(&self, method: reqwest::Method, url: &str) -> blocking::RequestBuilder {
+        self.client.request(method, url)
+    }
+    /// Sends `request`, retrying transient failures with exponential backoff.
+    pub fn send(
+        &self,
+        mut request: blocking::RequestBuilder,
+    ) -> Result<blocking::Response, reqwest::Error> {
+        let mut attempt = 0;
+        loop {
+            // bodies that can't be cloned are sent only once
+            let retry = request.try_clone();
+            let result = request.send();
+            let transient = match &result {
+                Ok(response) => transient_status(response.status()),
+                Err(e) => transient_error(e),
+            };
+            match retry {
+                Some(next) if transient && attempt < self.options.retries => {
+                    thread::sleep(self.options.backoff_before(attempt));
+                    attempt += 1;
+                    request = next;
+                }
+                _ => return result,
+            }
+        }
+    }
+}
+
+/// [`Http`] for the async client, needs a tokio runtime.
+#[cfg(feature = "http-async")]
+#[derive(Debug, Clone)]
+pub struct AsyncHttp {
+    client: reqwest::Client,
+    options: HttpOptions,
+}
+#[cfg(feature = "http-async")]
+impl AsyncHttp {
+    pub fn new(options: HttpOptions) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .timeout(options.timeout)
+            .connect_timeout(options.connect_timeout)
+            .user_agent(options.user_agent.as_str())
+            .build()?;
+        Ok(Self { client, options })
+    }
+    pub fn options(&self) -> &HttpOptions {
+        &self.options
+    }
+    pub fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client.get(url)
+    }
+    /// Sends `request`, retrying transient failures with exponential backoff.
+    pub async fn send(
+        &self,
+        mut request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let mut attempt = 0;
+        loop {
+            let retry = request.try_clone();
+            let result = request.send().await;
+            let transient = match &result {
+                Ok(response) => transient_status(response.status()),
+                Err(e) => transient_error(e),
+            };
+            match retry {
+                Some(next) if transient && attempt < self.options.retries => {
+                    tokio::time::sleep(self.options.backoff_before(attempt)).await;
+                    attempt += 1;
+                    request = next;
+                }
+                _ => return result,
+            }
+        }
+    }
+}
+/// Runs file access of the async client off the runtime's worker threads.
+#[cfg(feature = "http-async")]
+pub(crate) async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+fn transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+fn transient_error(e: &reqwest::Error) -> bool {
+    e.is_timeout() || e.is_connect()
+}
+pub(crate) fn parse<T: DeserializeOwned>(
+    body: &str,
+    status: CacheStatus,
+) -> Result<(T, bool), ApiError> {
+    Ok((
+        serde_json::from_str::<T>(body)?,
+        status == CacheStatus::Stale,
+    ))
+}
+/// The holiday data to use and whether it is a stale copy.
+pub(crate) fn pick_holidays(
+    campus: Campus,
+    remote: Result<(AcademicCalendarResponse, bool), ApiError>,
+) -> Result<(AcademicCalendarResponse, HolidaySource, bool), ApiError> {
+    Ok(match (remote, holidays::bundled(campus)) {
+        (Ok((remote, _)), Some(bundled)) if remote.version < bundled.version => {
+            (bundled, HolidaySource::BundledNewer, false)
+        }
+        (Ok((remote, stale)), _) => (remote, HolidaySource::Remote, stale),
+        (Err(_), Some(bundled)) => (bundled, HolidaySource::Bundled, false),
+        (Err(e), None) => return Err(e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calendar(version: u32) -> AcademicCalendarResponse {
+        AcademicCalendarResponse {
+            version,
+            semesters: vec![],
+        }
+    }
+
+    #[test]
+    fn picks_holidays() {
+        let bundled = holidays::bundled(Campus::Hyderabad).unwrap().version;
+        let source = |remote| pick_holidays(Campus::Hyderabad, remote).unwrap().1;
+        assert_eq!(
+            source(Ok((calendar(bundled), false))),
+            HolidaySource::Remote
+        );
+        assert_eq!(
+            source(Ok((calendar(bundled - 1), false))),
+            HolidaySource::BundledNewer
+        );
+        assert_eq!(
+            source(Err(ApiError::NoHolidayData(Campus::Hyderabad))),
+            HolidaySource::Bundled
+        );
+    }
+
+    #[test]
+    fn campuses_without_holiday_data() {
+        for campus in [Campus::Pilani, Campus::Goa, Campus::Dubai] {
+            assert_eq!(campus.profile().holidays_url, None);
+            let e = pick_holidays(campus, Err(ApiError::NoHolidayData(campus))).unwrap_err();
+            assert_eq!(
+                e.to_string(),
+                format!(
+                    "no holiday data for {}, a holidays.json has to be given",
+                    campus
+                )
+            );
+        }
+    }
+}
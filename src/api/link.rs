@@ -0,0 +1,140 @@
+use std::fmt;
+
+const CHRONO_HOST: &str = "chrono.crux-bphc.com";
+// path segments that can wrap the id in a chrono link, e.g. /view/<id> or /<id>/edit
+const LINK_KEYWORDS: [&str; 6] = ["view", "share", "edit", "tt", "timetable", "api"];
+const MAX_ID_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    Empty,
+    UnsupportedHost(String),
+    MissingId,
+    InvalidId(String),
+}
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the link is empty"),
+            Self::UnsupportedHost(host) => {
+                write!(f, "{} is not a chrono link (expected {})", host, CHRONO_HOST)
+            }
+            Self::MissingId => write!(f, "the link does not contain a timetable id"),
+            Self::InvalidId(id) => write!(f, "\"{}\" is not a valid timetable id", id),
+        }
+    }
+}
+impl std::error::Error for LinkError {}
+
+/// Extracts the timetable id from anything a user might paste: a share, view or
+/// edit link (with or without scheme, query, fragment or trailing slash) or a bare id.
+pub fn parse_timetable_id(link: &str) -> Result<String, LinkError> {
+    let link = link.trim();
+    if link.is_empty() {
+        return Err(LinkError::Empty);
+    }
+    let link = link
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches('/');
+    let without_scheme = match link.split_once("://") {
+        Some((_, rest)) => rest,
+        None => link,
+    };
+    let mut segments = without_scheme.split('/').filter(|s| !s.is_empty());
+    let first = segments.next().ok_or(LinkError::MissingId)?;
+
+    let path = if first.contains('.') || first.contains(':') || link.contains("://") {
+        let host = first.split(':').next().unwrap_or_default().to_lowercase();
+        if host != CHRONO_HOST && !host.ends_with(&format!(".{}", CHRONO_HOST)) {
+            return Err(LinkError::UnsupportedHost(host));
+        }
+        segments.collect::<Vec<&str>>()
+    } else {
+        std::iter::once(first).chain(segments).collect::<Vec<&str>>()
+    };
+
+    let id = path
+        .iter()
+        .rev()
+        .find(|segment| !LINK_KEYWORDS.contains(&segment.to_lowercase().as_str()))
+        .ok_or(LinkError::MissingId)?;
+    validate_id(id)?;
+    Ok(id.to_string())
+}
+
+fn validate_id(id: &str) -> Result<(), LinkError> {
+    let valid = id.len() <= MAX_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    match valid {
+        true => Ok(()),
+        false => Err(LinkError::InvalidId(id.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(link: &str) -> Result<String, LinkError> {
+        parse_timetable_id(link)
+    }
+
+    #[test]
+    fn links() {
+        for link in [
+            "https://chrono.crux-bphc.com/share/abc-123",
+            "https://chrono.crux-bphc.com/view/abc-123",
+            "https://chrono.crux-bphc.com/abc-123/edit",
+            "chrono.crux-bphc.com/tt/abc-123",
+            "http://www.chrono.crux-bphc.com:443/view/abc-123",
+        ] {
+            assert_eq!(id(link).as_deref(), Ok("abc-123"), "{}", link);
+        }
+    }
+
+    #[test]
+    fn bare_ids() {
+        assert_eq!(id("abc_123").as_deref(), Ok("abc_123"));
+        assert_eq!(id("  abc-123\n").as_deref(), Ok("abc-123"));
+        assert_eq!(id("view/abc-123").as_deref(), Ok("abc-123"));
+    }
+
+    #[test]
+    fn trailing_slashes_queries_and_fragments() {
+        for link in [
+            "https://chrono.crux-bphc.com/view/abc-123/",
+            "https://chrono.crux-bphc.com/view/abc-123//",
+            "https://chrono.crux-bphc.com/view/abc-123?ref=share",
+            "https://chrono.crux-bphc.com/abc-123/edit/?tab=1#top",
+            "abc-123/?x=1",
+        ] {
+            assert_eq!(id(link).as_deref(), Ok("abc-123"), "{}", link);
+        }
+    }
+
+    #[test]
+    fn rejects() {
+        assert_eq!(id("   "), Err(LinkError::Empty));
+        assert_eq!(
+            id("https://example.com/view/abc"),
+            Err(LinkError::UnsupportedHost("example.com".to_string()))
+        );
+        assert_eq!(
+            id("https://chrono.crux-bphc.com/"),
+            Err(LinkError::MissingId)
+        );
+        assert_eq!(
+            id("https://chrono.crux-bphc.com/view/edit"),
+            Err(LinkError::MissingId)
+        );
+        assert_eq!(
+            id("abc 123"),
+            Err(LinkError::InvalidId("abc 123".to_string()))
+        );
+        assert!(matches!(id(&"a".repeat(65)), Err(LinkError::InvalidId(_))));
+    }
+}
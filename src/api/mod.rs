@@ -1,5 +1,19 @@
+#[cfg(feature = "http-async")]
+pub mod async_client;
+pub mod builder;
+#[cfg(feature = "http")]
+pub mod cache;
+pub mod catalog;
+#[cfg(feature = "http-blocking")]
 pub mod client;
 #[allow(non_snake_case)]
-mod responses;
+pub mod responses;
 
 pub mod data;
+pub mod holidays;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod link;
+pub mod schema;
+pub mod snapshot;
+pub mod validate;
@@ -1,17 +1,20 @@
-use serde::Deserialize;
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct TimeTableResponse {
     pub id: String,
     pub name: String,
     // degrees: Vec<String>,
     // year: i32,
     pub acadYear: i32,
+    /// missing on older timetables
+    #[serde(default)]
+    pub semester: Option<u8>,
     pub sections: Vec<SectionResponse>,
     pub timings: Vec<String>,
     pub examTimes: Vec<String>,
 }
-#[derive(Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct SectionResponse {
     pub courseId: String,
     #[serde(rename = "type")]
@@ -20,31 +23,41 @@ pub struct SectionResponse {
     pub instructors: Vec<String>,
     pub roomTime: Vec<String>,
 }
-#[derive(Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[serde(transparent)]
 pub struct CourseResponse {
     pub courses: Vec<Course>,
 }
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 
 pub struct Course {
     pub id: String,
     pub code: String,
     pub name: String,
 }
-#[derive(Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct HolidayString {
     pub name: String,
     pub date: String,
+    /// gazetted, restricted or institute, names ending in "(H)" are gazetted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// full (default), morning, afternoon, after:<slot> or before:<slot>
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closure: Option<String>,
 }
-#[derive(Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct TimeTableChangeResponse {
     pub date: String,
     pub day: String,
 }
-#[derive(Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct HolidayResponse {
+    #[serde(default)]
+    pub acad_year: i32,
+    #[serde(default)]
+    pub semester: u8,
     pub classwork_start: String,
     pub classwork_end: String,
     pub midsem_start: String,
@@ -52,3 +65,103 @@ pub struct HolidayResponse {
     pub holidays: Vec<HolidayString>,
     pub time_table_changes: Vec<TimeTableChangeResponse>,
 }
+/// holidays.json, holding the holiday data of every semester it knows about.
+/// Files with a single semester at the top level are still accepted.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(from = "AcademicCalendarFormat")]
+pub struct AcademicCalendarResponse {
+    /// bumped on every edit so clients can tell a stale copy from the current one
+    pub version: u32,
+    pub semesters: Vec<HolidayResponse>,
+}
+#[derive(Deserialize)]
+struct SemestersFormat {
+    #[serde(default)]
+    version: u32,
+    semesters: Vec<HolidayResponse>,
+}
+enum AcademicCalendarFormat {
+    Semesters(SemestersFormat),
+    Single(HolidayResponse),
+}
+impl<'de> Deserialize<'de> for AcademicCalendarFormat {
+    // picked by the `semesters` key so a mistake is reported against the format it was meant for
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        if value.get("semesters").is_some() {
+            SemestersFormat::deserialize(value)
+                .map(Self::Semesters)
+                .map_err(|e| D::Error::custom(format!("holidays.json with semesters: {}", e)))
+        } else {
+            HolidayResponse::deserialize(value)
+                .map(Self::Single)
+                .map_err(|e| D::Error::custom(format!("single semester holidays.json: {}", e)))
+        }
+    }
+}
+impl From<AcademicCalendarFormat> for AcademicCalendarResponse {
+    fn from(format: AcademicCalendarFormat) -> Self {
+        match format {
+            AcademicCalendarFormat::Semesters(SemestersFormat { version, semesters }) => {
+                Self { version, semesters }
+            }
+            AcademicCalendarFormat::Single(semester) => Self {
+                version: 0,
+                semesters: vec![semester],
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEMESTER: &str = r#"{
+        "acad_year": 2024, "semester": 2,
+        "classwork_start": "2025-01-07T00:00:00+05:30", "classwork_end": "2025-04-29T00:00:00+05:30",
+        "midsem_start": "2025-03-03T00:00:00+05:30", "midsem_end": "2025-03-08T00:00:00+05:30",
+        "holidays": [], "time_table_changes": []
+    }"#;
+
+    #[test]
+    fn reads_both_formats() {
+        let single: AcademicCalendarResponse = serde_json::from_str(SEMESTER).unwrap();
+        assert_eq!(single.version, 0);
+        assert_eq!(single.semesters.len(), 1);
+        let semesters: AcademicCalendarResponse =
+            serde_json::from_str(&format!(r#"{{"version": 4, "semesters": [{}]}}"#, SEMESTER))
+                .unwrap();
+        assert_eq!(semesters.version, 4);
+        assert_eq!(semesters.semesters, single.semesters);
+    }
+
+    #[test]
+    fn errors_name_the_format() {
+        let e = serde_json::from_str::<AcademicCalendarResponse>(r#"{"semesters": [{}]}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            e.starts_with("holidays.json with semesters: missing field"),
+            "{}",
+            e
+        );
+        let e = serde_json::from_str::<AcademicCalendarResponse>(r#"{"holidays": []}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            e.starts_with("single semester holidays.json: missing field"),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn semester_is_optional() {
+        let response: TimeTableResponse = serde_json::from_str(
+            r#"{"id": "1", "name": "t", "acadYear": 2024, "sections": [], "timings": [], "examTimes": []}"#,
+        )
+        .unwrap();
+        assert_eq!(response.semester, None);
+    }
+}
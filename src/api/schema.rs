@@ -0,0 +1,162 @@
+//! The linked timetable as versioned json, to save, edit by hand and load it again
+//! without the chrono api.
+use super::data::TimeTable;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Bumped whenever a field is renamed or removed, added optional fields keep the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct VersionedRef<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    time_table: &'a TimeTable,
+}
+#[derive(Deserialize)]
+struct Versioned {
+    schema_version: u32,
+    #[serde(flatten)]
+    time_table: TimeTable,
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// written by a newer version of the app
+    UnsupportedVersion(u32),
+}
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Json(e) => write!(f, "invalid timetable json: {}", e),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "timetable json has schema version {}, this version reads up to {}",
+                version, SCHEMA_VERSION
+            ),
+        }
+    }
+}
+impl std::error::Error for SchemaError {}
+
+impl TimeTable {
+    /// Pretty printed json with a `schema_version` next to the timetable's fields.
+    pub fn to_json(&self) -> Result<String, SchemaError> {
+        serde_json::to_string_pretty(&VersionedRef {
+            schema_version: SCHEMA_VERSION,
+            time_table: self,
+        })
+        .map_err(SchemaError::Json)
+    }
+    pub fn from_json(json: &str) -> Result<Self, SchemaError> {
+        let versioned = serde_json::from_str::<Versioned>(json).map_err(SchemaError::Json)?;
+        match versioned.schema_version {
+            version if version > SCHEMA_VERSION => Err(SchemaError::UnsupportedVersion(version)),
+            _ => Ok(versioned.time_table),
+        }
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SchemaError> {
+        fs::write(path, self.to_json()?).map_err(SchemaError::Io)
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SchemaError> {
+        Self::from_json(&fs::read_to_string(path).map_err(SchemaError::Io)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data::{Closure, Course, Holiday, HolidayKind, Section, Timing};
+    use crate::campus::Campus;
+    use chrono::{NaiveDate, TimeZone, Utc, Weekday};
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    fn time_table() -> TimeTable {
+        TimeTable::builder(Campus::Hyderabad, date(8, 5), date(11, 29))
+            .id("42")
+            .name("mine")
+            .acad_year(2024)
+            .midsem(date(10, 7), date(10, 12))
+            .course(
+                Course::builder("CS F111", "Computer Programming")
+                    .lecture(Section::new(1).instructor("A").timing(Timing::new(
+                        Weekday::Mon,
+                        "F102",
+                        2,
+                        2,
+                    )))
+                    .compre(
+                        Utc.with_ymd_and_hms(2024, 12, 9, 3, 30, 0).unwrap(),
+                        Utc.with_ymd_and_hms(2024, 12, 9, 6, 30, 0).unwrap(),
+                    )
+                    .build(),
+            )
+            .holiday(
+                Holiday::new(
+                    "Diwali",
+                    Utc.with_ymd_and_hms(2024, 10, 30, 18, 30, 0).unwrap(),
+                )
+                .kind(HolidayKind::Gazetted)
+                .closure(Closure::AfterSlot(4)),
+            )
+            .day_swap(date(9, 14), Weekday::Mon)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let time_table = time_table();
+        let json = time_table.to_json().unwrap();
+        let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        assert_eq!(value["id"], "42");
+        assert_eq!(TimeTable::from_json(&json).unwrap(), time_table);
+
+        let path =
+            std::env::temp_dir().join(format!("chrono-to-ics-schema-{}.json", std::process::id()));
+        time_table.save(&path).unwrap();
+        assert_eq!(TimeTable::load(&path).unwrap(), time_table);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let mut value =
+            serde_json::from_str::<serde_json::Value>(&time_table().to_json().unwrap()).unwrap();
+        value["schema_version"] = (SCHEMA_VERSION + 1).into();
+        let error = TimeTable::from_json(&value.to_string()).unwrap_err();
+        assert!(
+            matches!(error, SchemaError::UnsupportedVersion(version) if version == SCHEMA_VERSION + 1)
+        );
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "timetable json has schema version {}, this version reads up to {}",
+                SCHEMA_VERSION + 1,
+                SCHEMA_VERSION
+            )
+        );
+    }
+
+    #[test]
+    fn needs_a_version() {
+        let json = serde_json::to_string(&time_table()).unwrap();
+        assert!(matches!(
+            TimeTable::from_json(&json),
+            Err(SchemaError::Json(_))
+        ));
+        assert!(matches!(
+            TimeTable::load("no such file.json"),
+            Err(SchemaError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,81 @@
+use super::catalog::CourseCatalog;
+use super::data::TimeTable;
+use super::holidays::HolidayOverride;
+use super::responses::{AcademicCalendarResponse, CourseResponse, TimeTableResponse};
+use crate::campus::Campus;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+#[cfg(feature = "http")]
+use std::path::PathBuf;
+
+pub const SNAPSHOT_FILE: &str = "timetable_snapshot.json";
+
+/// Where convert saves the snapshot, in the data dir next to the holiday override.
+#[cfg(feature = "http")]
+pub fn snapshot_path() -> PathBuf {
+    super::cache::data_dir().join(SNAPSHOT_FILE)
+}
+
+/// Everything needed to rebuild the last converted timetable without network.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snapshot {
+    pub campus: Campus,
+    pub fetched_at: DateTime<Utc>,
+    pub time_table: TimeTableResponse,
+    /// only the courses the timetable has sections of
+    pub courses: CourseResponse,
+    pub academic_calendar: AcademicCalendarResponse,
+    /// restricted holidays taken, `None` when all restricted holidays are applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub taken_restricted: Option<Vec<String>>,
+}
+impl Snapshot {
+    pub fn new(
+        campus: Campus,
+        time_table: &TimeTableResponse,
+        catalog: &CourseCatalog,
+        academic_calendar: &AcademicCalendarResponse,
+    ) -> Self {
+        let courses = catalog.subset(
+            time_table
+                .sections
+                .iter()
+                .map(|section| section.courseId.as_str()),
+        );
+        Self {
+            campus,
+            fetched_at: Utc::now(),
+            time_table: time_table.clone(),
+            courses,
+            academic_calendar: academic_calendar.clone(),
+            taken_restricted: None,
+        }
+    }
+    /// Links the saved responses again, with the current holiday override applied.
+    pub fn time_table(&self, holiday_override: Option<&HolidayOverride>) -> Option<TimeTable> {
+        let mut time_table = TimeTable::new(
+            &self.time_table,
+            &CourseCatalog::from(&self.courses),
+            &self.academic_calendar,
+            holiday_override,
+            self.campus,
+        )?;
+        if let Some(taken) = &self.taken_restricted {
+            time_table.keep_restricted_holidays(taken);
+        }
+        Some(time_table)
+    }
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        serde_json::from_str(&data).map_err(io::Error::from)
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+    }
+}
@@ -0,0 +1,333 @@
+use super::data::{Closure, HolidayKind, WeekdayWrapper};
+use super::holidays::acad_year_label;
+use super::responses::{AcademicCalendarResponse, HolidayResponse};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, Weekday};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub message: String,
+}
+impl ValidationIssue {
+    fn error(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+        }
+    }
+    fn warning(message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+        }
+    }
+}
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning: {}", self.message),
+            Severity::Error => write!(f, "error: {}", self.message),
+        }
+    }
+}
+
+/// Checks every semester of a holiday file, messages are prefixed with the semester.
+pub fn validate_calendar(academic_calendar: &AcademicCalendarResponse) -> Vec<ValidationIssue> {
+    let mut issues: Vec<ValidationIssue> = vec![];
+    if academic_calendar.semesters.is_empty() {
+        issues.push(ValidationIssue::error("no semesters".to_string()));
+    }
+    for (i, semester) in academic_calendar.semesters.iter().enumerate() {
+        let label = format!(
+            "semester {} of {}",
+            semester.semester,
+            acad_year_label(semester.acad_year)
+        );
+        if academic_calendar.semesters[..i].iter().any(|other| {
+            other.acad_year == semester.acad_year && other.semester == semester.semester
+        }) {
+            issues.push(ValidationIssue::error(format!("{} appears twice", label)));
+        }
+        issues.extend(validate(semester).into_iter().map(|issue| ValidationIssue {
+            message: format!("{}: {}", label, issue.message),
+            ..issue
+        }));
+    }
+    issues
+}
+
+/// Checks the holiday data of one semester for mistakes `TimeTable::new` would silently skip.
+pub fn validate(holidays: &HolidayResponse) -> Vec<ValidationIssue> {
+    let mut issues: Vec<ValidationIssue> = vec![];
+    let mut date = |field: &str, value: &str| match parse_date(value) {
+        Ok(date) => Some(date),
+        Err(message) => {
+            issues.push(ValidationIssue::error(format!("{} {}", field, message)));
+            None
+        }
+    };
+    let classwork_start = date("classwork_start", &holidays.classwork_start);
+    let classwork_end = date("classwork_end", &holidays.classwork_end);
+    // the midsem window is optional
+    let midsem = match (
+        holidays.midsem_start.is_empty(),
+        holidays.midsem_end.is_empty(),
+    ) {
+        (true, true) => None,
+        _ => Some((
+            date("midsem_start", &holidays.midsem_start),
+            date("midsem_end", &holidays.midsem_end),
+        )),
+    };
+    let holiday_dates = holidays
+        .holidays
+        .iter()
+        .map(|holiday| {
+            (
+                holiday.name.as_str(),
+                date(
+                    &format!("date of holiday \"{}\"", holiday.name),
+                    &holiday.date,
+                ),
+            )
+        })
+        .collect::<Vec<(&str, Option<NaiveDate>)>>();
+    let change_dates = holidays
+        .time_table_changes
+        .iter()
+        .map(|change| date("date of day swap", &change.date))
+        .collect::<Vec<Option<NaiveDate>>>();
+
+    if let (Some(start), Some(end)) = (classwork_start, classwork_end) {
+        if end < start {
+            issues.push(ValidationIssue::error(format!(
+                "classwork ends ({}) before it starts ({})",
+                end, start
+            )));
+        }
+        let outside = |date: &NaiveDate| *date < start || *date > end;
+        for (name, date) in holiday_dates.iter() {
+            match date {
+                Some(date) if outside(date) => issues.push(ValidationIssue::warning(format!(
+                    "holiday \"{}\" on {} is outside classwork",
+                    name, date
+                ))),
+                _ => {}
+            }
+        }
+        for date in change_dates.iter().flatten().filter(|date| outside(date)) {
+            issues.push(ValidationIssue::warning(format!(
+                "day swap on {} is outside classwork",
+                date
+            )));
+        }
+    }
+    if let Some((Some(start), Some(end))) = midsem {
+        if end < start {
+            issues.push(ValidationIssue::error(format!(
+                "midsem ends ({}) before it starts ({})",
+                end, start
+            )));
+        }
+    }
+    for holiday in holidays.holidays.iter() {
+        if let Some(Err(e)) = holiday
+            .kind
+            .as_ref()
+            .map(|kind| kind.parse::<HolidayKind>())
+        {
+            issues.push(ValidationIssue::warning(format!(
+                "holiday \"{}\": {}, treated as an institute holiday",
+                holiday.name, e
+            )));
+        }
+        if let Some(Err(e)) = holiday.closure.as_ref().map(|c| c.parse::<Closure>()) {
+            issues.push(ValidationIssue::warning(format!(
+                "holiday \"{}\": {}, treated as closing the whole day",
+                holiday.name, e
+            )));
+        }
+    }
+    for (i, (name, date)) in holiday_dates.iter().enumerate() {
+        let Some(date) = date else {
+            continue;
+        };
+        if holiday_dates[..i]
+            .iter()
+            .any(|(_, other)| other == &Some(*date))
+        {
+            issues.push(ValidationIssue::warning(format!(
+                "more than one holiday on {}",
+                date
+            )));
+        }
+        if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            issues.push(ValidationIssue::warning(format!(
+                "holiday \"{}\" falls on a {}, there are no classes to cancel",
+                name,
+                date.weekday()
+            )));
+        }
+    }
+    for (i, (change, date)) in holidays
+        .time_table_changes
+        .iter()
+        .zip(change_dates.iter())
+        .enumerate()
+    {
+        if change.day.parse::<WeekdayWrapper>().is_err() {
+            issues.push(ValidationIssue::error(format!(
+                "day swap has invalid day \"{}\", expected one of M, T, W, Th, F",
+                change.day
+            )));
+        }
+        let Some(date) = date else {
+            continue;
+        };
+        if change_dates[..i].contains(&Some(*date)) {
+            issues.push(ValidationIssue::error(format!(
+                "more than one day swap on {}",
+                date
+            )));
+        }
+        if holiday_dates.iter().any(|(_, other)| other == &Some(*date)) {
+            issues.push(ValidationIssue::warning(format!(
+                "day swap on {} is also a holiday",
+                date
+            )));
+        }
+    }
+    issues
+}
+
+// the local date of an rfc3339 date time, with an explanation when it isn't one
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    if let Ok(date_time) = value.parse::<DateTime<FixedOffset>>() {
+        return Ok(date_time.date_naive());
+    }
+    if value.parse::<NaiveDateTime>().is_ok() || value.parse::<NaiveDate>().is_ok() {
+        return Err(format!("\"{}\" has no timezone", value));
+    }
+    Err(format!("\"{}\" is not a valid date", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::responses::{HolidayString, TimeTableChangeResponse};
+
+    fn semester() -> HolidayResponse {
+        HolidayResponse {
+            acad_year: 2024,
+            semester: 1,
+            classwork_start: "2024-08-05T00:00:00+05:30".to_string(),
+            classwork_end: "2024-11-29T00:00:00+05:30".to_string(),
+            midsem_start: "2024-10-07T00:00:00+05:30".to_string(),
+            midsem_end: "2024-10-12T00:00:00+05:30".to_string(),
+            holidays: vec![holiday("Independence Day", "2024-08-15T00:00:00+05:30")],
+            time_table_changes: vec![TimeTableChangeResponse {
+                date: "2024-08-17T00:00:00+05:30".to_string(),
+                day: "Th".to_string(),
+            }],
+        }
+    }
+
+    fn holiday(name: &str, date: &str) -> HolidayString {
+        HolidayString {
+            name: name.to_string(),
+            date: date.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn messages(issues: Vec<ValidationIssue>, severity: Severity) -> Vec<String> {
+        issues
+            .into_iter()
+            .filter(|issue| issue.severity == severity)
+            .map(|issue| issue.message)
+            .collect()
+    }
+
+    #[test]
+    fn clean_semester() {
+        assert_eq!(validate(&semester()), vec![]);
+    }
+
+    #[test]
+    fn errors() {
+        let mut holidays = semester();
+        holidays.classwork_end = "2024-07-01T00:00:00+05:30".to_string();
+        holidays.midsem_start = "2024-10-07T00:00:00".to_string();
+        holidays.holidays.push(holiday("Diwali", "31/10/2024"));
+        holidays.time_table_changes.push(TimeTableChangeResponse {
+            date: "2024-08-17T00:00:00+05:30".to_string(),
+            day: "Sa".to_string(),
+        });
+        assert_eq!(
+            messages(validate(&holidays), Severity::Error),
+            vec![
+                "midsem_start \"2024-10-07T00:00:00\" has no timezone",
+                "date of holiday \"Diwali\" \"31/10/2024\" is not a valid date",
+                "classwork ends (2024-07-01) before it starts (2024-08-05)",
+                "day swap has invalid day \"Sa\", expected one of M, T, W, Th, F",
+                "more than one day swap on 2024-08-17",
+            ]
+        );
+    }
+
+    #[test]
+    fn warnings() {
+        let mut holidays = semester();
+        holidays.midsem_start = String::new();
+        holidays.midsem_end = String::new();
+        holidays.holidays.extend([
+            holiday("Holi", "2024-08-15T00:00:00+05:30"),
+            holiday("Sunday", "2024-08-18T00:00:00+05:30"),
+            holiday("New Year", "2025-01-01T00:00:00+05:30"),
+            HolidayString {
+                kind: Some("national".to_string()),
+                closure: Some("evening".to_string()),
+                ..holiday("Onam", "2024-09-16T00:00:00+05:30")
+            },
+        ]);
+        assert_eq!(
+            messages(validate(&holidays), Severity::Warning),
+            vec![
+                "holiday \"New Year\" on 2025-01-01 is outside classwork",
+                "holiday \"Onam\": invalid holiday kind \"national\", expected gazetted, restricted \
+                 or institute, treated as an institute holiday",
+                "holiday \"Onam\": invalid closure \"evening\", expected full, morning, afternoon, \
+                 after:<slot> or before:<slot>, treated as closing the whole day",
+                "more than one holiday on 2024-08-15",
+                "holiday \"Sunday\" falls on a Sun, there are no classes to cancel",
+            ]
+        );
+    }
+
+    #[test]
+    fn calendar() {
+        let empty = AcademicCalendarResponse::default();
+        assert_eq!(
+            messages(validate_calendar(&empty), Severity::Error),
+            vec!["no semesters"]
+        );
+        let mut calendar = AcademicCalendarResponse {
+            version: 1,
+            semesters: vec![semester(), semester()],
+        };
+        calendar.semesters[1].classwork_start = "soon".to_string();
+        assert_eq!(
+            messages(validate_calendar(&calendar), Severity::Error),
+            vec![
+                "semester 1 of 2024-25 appears twice",
+                "semester 1 of 2024-25: classwork_start \"soon\" is not a valid date",
+            ]
+        );
+    }
+}
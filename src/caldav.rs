@@ -0,0 +1,641 @@
+//! Pushes the generated events to a CalDAV calendar (Nextcloud, Radicale, Fastmail, ...),
+//! one resource per event uid, so the calendar follows the timetable without imports.
+use crate::api::data::TimeTable;
+use crate::api::http::Http;
+use crate::ics::{self, generated_for, property, CalendarOptions};
+use icalendar::{Calendar, Component, Event};
+use regex::Regex;
+use reqwest::blocking::RequestBuilder;
+use reqwest::header::{CONTENT_TYPE, IF_MATCH, IF_NONE_MATCH};
+use reqwest::{Method, StatusCode, Url};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Fingerprint of an event as generated, servers rewrite events so their copy can't be
+/// compared with ours directly.
+const HASH_PROPERTY: &str = "X-CHRONO-TO-ICS-HASH";
+/// Every event of the collection with its etag and content.
+const CALENDAR_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<C:calendar-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <D:prop><D:getetag/><C:calendar-data/></D:prop>
+  <C:filter><C:comp-filter name="VCALENDAR"><C:comp-filter name="VEVENT"/></C:comp-filter></C:filter>
+</C:calendar-query>"#;
+
+#[derive(Debug)]
+pub enum CalDavError {
+    InvalidUrl(String),
+    Network(reqwest::Error),
+    /// the server answered with an unexpected status
+    Status {
+        method: Method,
+        url: String,
+        status: StatusCode,
+    },
+}
+impl fmt::Display for CalDavError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUrl(e) => write!(f, "invalid collection url: {}", e),
+            Self::Network(e) => write!(f, "{}", e),
+            Self::Status {
+                method,
+                url,
+                status,
+            } => write!(f, "{} {} failed: {}", method, url, status),
+        }
+    }
+}
+impl std::error::Error for CalDavError {}
+
+/// What a push did, counted in events.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PushReport {
+    pub created: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+    /// uids of events changed on the server while pushing, left as they are
+    pub conflicts: Vec<String>,
+}
+impl fmt::Display for PushReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} created, {} updated, {} deleted, {} unchanged",
+            self.created, self.updated, self.deleted, self.unchanged
+        )?;
+        if !self.conflicts.is_empty() {
+            write!(f, ", {} changed on the server", self.conflicts.len())?;
+        }
+        Ok(())
+    }
+}
+
+/// An event already in the collection.
+#[derive(Debug)]
+struct Remote {
+    url: Url,
+    etag: Option<String>,
+    hash: Option<String>,
+    /// the event as the server has it
+    data: String,
+}
+
+enum Sent {
+    Done,
+    /// If-Match or If-None-Match failed
+    Conflict,
+}
+
+/// A CalDAV collection, e.g. `https://cloud.example.com/remote.php/dav/calendars/me/timetable/`.
+#[derive(Debug, Clone)]
+pub struct CalDav {
+    http: Http,
+    collection: Url,
+    credentials: Option<(String, String)>,
+}
+impl CalDav {
+    pub fn new(http: Http, collection: &str) -> Result<Self, CalDavError> {
+        // event files and relative hrefs are resolved against it
+        let collection = match collection.ends_with('/') {
+            true => collection.to_string(),
+            false => format!("{}/", collection),
+        };
+        Ok(Self {
+            http,
+            collection: Url::parse(&collection)
+                .map_err(|e| CalDavError::InvalidUrl(e.to_string()))?,
+            credentials: None,
+        })
+    }
+    /// Basic auth, use an app password where the server has them.
+    pub fn credentials(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+    /// Creates the events of `time_table` missing from the collection, updates the changed
+    /// ones and deletes the ones of this timetable it no longer has. Events of other
+    /// timetables and events not generated by this app are left alone.
+    pub fn push(
+        &self,
+        time_table: &TimeTable,
+        options: &CalendarOptions,
+    ) -> Result<PushReport, CalDavError> {
+        let events = ics::events(time_table, options);
+        let uids = events
+            .iter()
+            .filter_map(|generated| generated.event.get_uid().map(str::to_string))
+            .collect::<HashSet<String>>();
+        let mut remote = self
+            .list()?
+            .into_iter()
+            .filter(|(_, existing)| generated_for(&existing.data, &time_table.id, &uids))
+            .collect::<HashMap<String, Remote>>();
+        let mut report = PushReport::default();
+        for generated in events {
+            let Some(uid) = generated.event.get_uid().map(str::to_string) else {
+                continue;
+            };
+            let (body, hash) = resource(generated.event);
+            let (sent, count) = match remote.remove(&uid) {
+                Some(existing) if existing.hash.as_deref() == Some(hash.as_str()) => {
+                    report.unchanged += 1;
+                    continue;
+                }
+                Some(existing) => {
+                    let sent = self.send(Method::PUT, &existing.url, |request| {
+                        let request = request.header(CONTENT_TYPE, ICS).body(body);
+                        match &existing.etag {
+                            Some(etag) => request.header(IF_MATCH, etag),
+                            None => request,
+                        }
+                    })?;
+                    (sent, &mut report.updated)
+                }
+                None => {
+                    let url = self
+                        .collection
+                        .join(&file_name(&uid))
+                        .map_err(|e| CalDavError::InvalidUrl(e.to_string()))?;
+                    let sent = self.send(Method::PUT, &url, |request| {
+                        request
+                            .header(CONTENT_TYPE, ICS)
+                            .header(IF_NONE_MATCH, "*")
+                            .body(body)
+                    })?;
+                    (sent, &mut report.created)
+                }
+            };
+            match sent {
+                Sent::Done => *count += 1,
+                Sent::Conflict => report.conflicts.push(uid),
+            }
+        }
+        for (uid, existing) in remote {
+            let sent = self.send(Method::DELETE, &existing.url, |request| {
+                match &existing.etag {
+                    Some(etag) => request.header(IF_MATCH, etag),
+                    None => request,
+                }
+            })?;
+            match sent {
+                Sent::Done => report.deleted += 1,
+                Sent::Conflict => report.conflicts.push(uid),
+            }
+        }
+        Ok(report)
+    }
+    /// The events in the collection by uid.
+    fn list(&self) -> Result<HashMap<String, Remote>, CalDavError> {
+        // a valid method name, from_bytes can't fail on it
+        let report = Method::from_bytes(b"REPORT").unwrap();
+        let request = self
+            .authorized(report.clone(), &self.collection)
+            .header("Depth", "1")
+            .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+            .body(CALENDAR_QUERY);
+        let response = self.http.send(request).map_err(CalDavError::Network)?;
+        if !response.status().is_success() {
+            return Err(CalDavError::Status {
+                method: report,
+                url: self.collection.to_string(),
+                status: response.status(),
+            });
+        }
+        let body = response.text().map_err(CalDavError::Network)?;
+        Ok(responses(&body)
+            .into_iter()
+            .filter_map(|(href, etag, data)| {
+                let data = data?;
+                Some((
+                    property(&data, "UID")?,
+                    Remote {
+                        url: self.collection.join(&href).ok()?,
+                        etag,
+                        hash: property(&data, HASH_PROPERTY),
+                        data,
+                    },
+                ))
+            })
+            .collect())
+    }
+    fn authorized(&self, method: Method, url: &Url) -> RequestBuilder {
+        let request = self.http.request(method, url.as_str());
+        match &self.credentials {
+            Some((username, password)) => request.basic_auth(username, Some(password)),
+            None => request,
+        }
+    }
+    fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        build: impl FnOnce(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Sent, CalDavError> {
+        let request = build(self.authorized(method.clone(), url));
+        let status = self
+            .http
+            .send(request)
+            .map_err(CalDavError::Network)?
+            .status();
+        match status {
+            status if status.is_success() => Ok(Sent::Done),
+            StatusCode::PRECONDITION_FAILED => Ok(Sent::Conflict),
+            // deleted by someone else already
+            StatusCode::NOT_FOUND if method == Method::DELETE => Ok(Sent::Done),
+            status => Err(CalDavError::Status {
+                method,
+                url: url.to_string(),
+                status,
+            }),
+        }
+    }
+}
+
+const ICS: &str = "text/calendar; charset=utf-8";
+
+/// The event in a calendar of its own, and the fingerprint of its content.
+fn resource(mut event: Event) -> (String, String) {
+    let mut calendar = Calendar::new();
+    calendar.push(event.clone());
+    // DTSTAMP is when it was generated, not part of the content
+    let content = calendar
+        .to_string()
+        .lines()
+        .filter(|line| !line.starts_with("DTSTAMP"))
+        .collect::<String>();
+    let hash = format!("{:016x}", fnv1a(content.as_bytes()));
+    event.add_property(HASH_PROPERTY, &hash);
+    let mut calendar = Calendar::new();
+    calendar.push(event);
+    (calendar.to_string(), hash)
+}
+
+/// 64 bit FNV-1a. Unlike `DefaultHasher` it is the same in every build, so the hashes on
+/// the server still match after an upgrade.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn file_name(uid: &str) -> String {
+    let name = uid
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || "-_.@".contains(c) {
+            true => c,
+            false => '_',
+        })
+        .collect::<String>();
+    format!("{}.ics", name)
+}
+
+/// href, etag and calendar data of every response in a multistatus body.
+fn responses(body: &str) -> Vec<(String, Option<String>, Option<String>)> {
+    // namespace prefixes differ between servers
+    let tag = |name: &str| {
+        Regex::new(&format!(
+            r"(?s)<(?:[\w-]+:)?{0}(?:\s[^>]*)?>(.*?)</(?:[\w-]+:)?{0}>",
+            name
+        ))
+        .unwrap()
+    };
+    let (response, href, etag, data) = (
+        tag("response"),
+        tag("href"),
+        tag("getetag"),
+        tag("calendar-data"),
+    );
+    let text = |re: &Regex, xml: &str| {
+        re.captures(xml)
+            .and_then(|caps| caps.get(1))
+            .map(|text| unescape(text.as_str().trim()))
+    };
+    response
+        .captures_iter(body)
+        .filter_map(|caps| {
+            let xml = caps.get(1)?.as_str();
+            Some((text(&href, xml)?, text(&etag, xml), text(&data, xml)))
+        })
+        .collect()
+}
+fn unescape(text: &str) -> String {
+    // character data is taken as it is
+    if let Some(text) = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+    {
+        return text.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data::{Course, Section, Timing};
+    use crate::api::http::HttpOptions;
+    use crate::campus::Campus;
+    use chrono::{NaiveDate, Weekday};
+
+    #[test]
+    fn reads_multistatus() {
+        let body = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/cal/a.ics</d:href>
+    <d:propstat><d:prop>
+      <d:getetag>&quot;1&quot;</d:getetag>
+      <cal:calendar-data>BEGIN:VCALENDAR&#13;
+UID:a&amp;b&#13;
+END:VCALENDAR</cal:calendar-data>
+    </d:prop></d:propstat>
+  </d:response>
+  <response xmlns="DAV:">
+    <href>/cal/b.ics</href>
+    <propstat><prop><getetag>"2"</getetag></prop></propstat>
+  </response>
+</d:multistatus>"#;
+        assert_eq!(
+            responses(body),
+            vec![
+                (
+                    "/cal/a.ics".to_string(),
+                    Some("\"1\"".to_string()),
+                    Some("BEGIN:VCALENDAR\r\nUID:a&b\r\nEND:VCALENDAR".to_string())
+                ),
+                ("/cal/b.ics".to_string(), Some("\"2\"".to_string()), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn unescapes_xml() {
+        assert_eq!(unescape("&lt;a&gt; &amp;lt; &apos;&quot;"), "<a> &lt; '\"");
+        assert_eq!(unescape("<![CDATA[a &amp; b]]>"), "a &amp; b");
+    }
+
+    #[test]
+    fn hash_is_stable() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn file_names() {
+        assert_eq!(
+            file_name("42-CSF111-lecture@chrono-to-ics"),
+            "42-CSF111-lecture@chrono-to-ics.ics"
+        );
+        assert_eq!(file_name("a/b c"), "a_b_c.ics");
+    }
+
+    #[cfg(feature = "server")]
+    mod push {
+        use super::*;
+        use std::sync::{Arc, Mutex};
+        use std::thread;
+
+        /// method, path, If-Match and If-None-Match of a request
+        type Sent = (String, String, Option<String>, Option<String>);
+
+        /// A collection answering REPORT with `remote` (uid, timetable id, etag, hash, an
+        /// empty id for events from before the id was stored) and every PUT and DELETE with
+        /// `status`, remembering what was sent.
+        fn collection(
+            remote: &[(&str, &str, &str, &str)],
+            status: u16,
+        ) -> (CalDav, Arc<Mutex<Vec<Sent>>>) {
+            let multistatus = remote
+                .iter()
+                .map(|(uid, id, etag, hash)| {
+                    let id = match id.is_empty() {
+                        true => String::new(),
+                        false => format!("{}:{}\n", ics::TIME_TABLE_PROPERTY, id),
+                    };
+                    format!(
+                        "<d:response><d:href>/cal/{}</d:href><d:propstat><d:prop>\
+                         <d:getetag>&quot;{}&quot;</d:getetag><c:calendar-data>\
+                         BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:{}\n{}{}:{}\nEND:VEVENT\nEND:VCALENDAR\
+                         </c:calendar-data></d:prop></d:propstat></d:response>",
+                        file_name(uid),
+                        etag,
+                        uid,
+                        id,
+                        HASH_PROPERTY,
+                        hash
+                    )
+                })
+                .collect::<String>();
+            let body = format!(
+                "<d:multistatus xmlns:d=\"DAV:\" xmlns:c=\"urn:ietf:params:xml:ns:caldav\">{}</d:multistatus>",
+                multistatus
+            );
+            let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+            let addr = server.server_addr().to_ip().unwrap();
+            let sent = Arc::new(Mutex::new(vec![]));
+            let log = sent.clone();
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    let header = |name: &'static str| {
+                        request
+                            .headers()
+                            .iter()
+                            .find(|header| header.field.equiv(name))
+                            .map(|header| header.value.to_string())
+                    };
+                    let method = request.method().to_string();
+                    let response = match method.as_str() {
+                        "REPORT" => {
+                            tiny_http::Response::from_string(body.clone()).with_status_code(207)
+                        }
+                        _ => {
+                            log.lock().unwrap().push((
+                                method,
+                                request.url().to_string(),
+                                header("If-Match"),
+                                header("If-None-Match"),
+                            ));
+                            tiny_http::Response::from_string("").with_status_code(status)
+                        }
+                    };
+                    let _ = request.respond(response);
+                }
+            });
+            let http = Http::new(HttpOptions {
+                retries: 0,
+                ..Default::default()
+            })
+            .unwrap();
+            let caldav = CalDav::new(http, &format!("http://{}/cal", addr)).unwrap();
+            (caldav, sent)
+        }
+
+        fn time_table() -> TimeTable {
+            time_table_with_id("42")
+        }
+        fn time_table_with_id(id: &str) -> TimeTable {
+            TimeTable::builder(
+                Campus::Hyderabad,
+                NaiveDate::from_ymd_opt(2024, 8, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 11, 30).unwrap(),
+            )
+            .id(id)
+            .course(
+                Course::builder("CS F111", "Computer Programming")
+                    .lecture(
+                        Section::new(1)
+                            .timing(Timing::new(Weekday::Mon, "F102", 2, 2))
+                            .timing(Timing::new(Weekday::Wed, "F102", 4, 4)),
+                    )
+                    .tutorial(Section::new(1).timing(Timing::new(Weekday::Fri, "F105", 6, 6)))
+                    .build(),
+            )
+            .build()
+            .unwrap()
+        }
+        /// uid and hash of every generated event
+        fn generated(time_table: &TimeTable) -> Vec<(String, String)> {
+            ics::events(time_table, &CalendarOptions::default())
+                .into_iter()
+                .map(|generated| {
+                    let uid = generated.event.get_uid().unwrap().to_string();
+                    (uid, resource(generated.event).1)
+                })
+                .collect()
+        }
+
+        #[test]
+        fn creates_updates_and_deletes() {
+            let time_table = time_table();
+            let generated = generated(&time_table);
+            assert_eq!(generated.len(), 3);
+            let (caldav, sent) = collection(
+                &[
+                    (&generated[0].0, "42", "1", &generated[0].1),
+                    (&generated[1].0, "42", "2", "outdated"),
+                    ("42-CSF111-lab-1-Tue@chrono-to-ics", "42", "3", "dropped"),
+                    (
+                        "7-CSF111-lecture-1-Mon@chrono-to-ics",
+                        "7",
+                        "4",
+                        "other timetable",
+                    ),
+                    ("dentist@example.com", "", "5", "not generated"),
+                ],
+                204,
+            );
+            let report = caldav
+                .push(&time_table, &CalendarOptions::default())
+                .unwrap();
+            assert_eq!(
+                report,
+                PushReport {
+                    created: 1,
+                    updated: 1,
+                    deleted: 1,
+                    unchanged: 1,
+                    conflicts: vec![],
+                }
+            );
+            let mut sent = sent.lock().unwrap().clone();
+            sent.sort();
+            let path = |uid: &str| format!("/cal/{}", file_name(uid));
+            assert_eq!(
+                sent,
+                vec![
+                    (
+                        "DELETE".to_string(),
+                        path("42-CSF111-lab-1-Tue@chrono-to-ics"),
+                        Some("\"3\"".to_string()),
+                        None
+                    ),
+                    (
+                        "PUT".to_string(),
+                        path(&generated[1].0),
+                        Some("\"2\"".to_string()),
+                        None
+                    ),
+                    (
+                        "PUT".to_string(),
+                        path(&generated[2].0),
+                        None,
+                        Some("*".to_string())
+                    ),
+                ]
+            );
+        }
+
+        #[test]
+        fn leaves_timetables_with_a_longer_id_alone() {
+            let time_table = time_table_with_id("abc");
+            let generated = generated(&time_table);
+            let longer = generated_uid_of(&generated[0].0, "abc-def");
+            let (caldav, sent) = collection(
+                &[
+                    // from before the id was stored, matched by its uid
+                    (&generated[0].0, "", "1", "no id"),
+                    (&longer, "abc-def", "2", "longer id"),
+                    (
+                        &generated_uid_of(&generated[1].0, "abc-def"),
+                        "",
+                        "3",
+                        "no id",
+                    ),
+                ],
+                204,
+            );
+            let report = caldav
+                .push(&time_table, &CalendarOptions::default())
+                .unwrap();
+            assert_eq!((report.created, report.updated, report.deleted), (2, 1, 0));
+            assert!(sent
+                .lock()
+                .unwrap()
+                .iter()
+                .all(|(_, path, _, _)| !path.starts_with("/cal/abc-def-")));
+        }
+        /// `uid` as generated for timetable `id` instead
+        fn generated_uid_of(uid: &str, id: &str) -> String {
+            format!("{}{}", id, uid.strip_prefix("abc").unwrap())
+        }
+
+        #[test]
+        fn reports_conflicts() {
+            let time_table = time_table();
+            let generated = generated(&time_table);
+            let (caldav, _) = collection(&[(&generated[0].0, "42", "1", "changed")], 412);
+            let report = caldav
+                .push(&time_table, &CalendarOptions::default())
+                .unwrap();
+            assert_eq!((report.created, report.updated), (0, 0));
+            let mut conflicts = report.conflicts.clone();
+            conflicts.sort();
+            let mut uids = generated
+                .into_iter()
+                .map(|(uid, _)| uid)
+                .collect::<Vec<String>>();
+            uids.sort();
+            assert_eq!(conflicts, uids);
+        }
+
+        #[test]
+        fn fails_on_other_statuses() {
+            let time_table = time_table();
+            let (caldav, _) = collection(&[], 403);
+            assert!(matches!(
+                caldav.push(&time_table, &CalendarOptions::default()),
+                Err(CalDavError::Status {
+                    status: StatusCode::FORBIDDEN,
+                    ..
+                })
+            ));
+        }
+    }
+}
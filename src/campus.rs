@@ -0,0 +1,138 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+const HOLIDAYS_BASE_URL: &str =
+    "https://raw.githubusercontent.com/lokesh185/chrono-to-ics-prototype/master";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Campus {
+    Pilani,
+    Goa,
+    #[default]
+    Hyderabad,
+    Dubai,
+}
+
+/// Everything that differs between campuses when building a calendar.
+#[derive(Debug, Clone)]
+pub struct CampusProfile {
+    pub timezone: Tz,
+    pub slots: SlotSchedule,
+    /// `None` for campuses nobody publishes holiday data for yet
+    pub holidays_url: Option<String>,
+    /// holiday data compiled into the binary, used when `holidays_url` is unreachable
+    pub bundled_holidays: Option<&'static str>,
+    pub calendar_name: &'static str,
+}
+
+/// Wall clock times of the numbered class slots, slot 1 being the first of the day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotSchedule {
+    pub first_slot: NaiveTime,
+    /// time between the start of two consecutive slots
+    pub slot_minutes: u32,
+    /// time a single slot actually lasts
+    pub class_minutes: u32,
+}
+impl SlotSchedule {
+    pub fn start(&self, slot: u8) -> NaiveTime {
+        let offset = self.slot_minutes * (slot.max(1) as u32 - 1);
+        self.first_slot + chrono::Duration::minutes(offset as i64)
+    }
+    pub fn end(&self, slot: u8) -> NaiveTime {
+        self.start(slot) + chrono::Duration::minutes(self.class_minutes as i64)
+    }
+}
+impl Default for SlotSchedule {
+    // 8:00 - 8:50, 9:00 - 9:50 ...
+    fn default() -> Self {
+        Self {
+            first_slot: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            slot_minutes: 60,
+            class_minutes: 50,
+        }
+    }
+}
+
+impl Campus {
+    pub const ALL: [Campus; 4] = [
+        Campus::Pilani,
+        Campus::Goa,
+        Campus::Hyderabad,
+        Campus::Dubai,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pilani => "pilani",
+            Self::Goa => "goa",
+            Self::Hyderabad => "hyderabad",
+            Self::Dubai => "dubai",
+        }
+    }
+    pub fn profile(&self) -> CampusProfile {
+        let (timezone, calendar_name) = match self {
+            Self::Pilani => (chrono_tz::Asia::Kolkata, "pilani calendar"),
+            Self::Goa => (chrono_tz::Asia::Kolkata, "goa calendar"),
+            Self::Hyderabad => (chrono_tz::Asia::Kolkata, "bphc calendar"),
+            Self::Dubai => (chrono_tz::Asia::Dubai, "dubai calendar"),
+        };
+        CampusProfile {
+            timezone,
+            slots: SlotSchedule::default(),
+            holidays_url: match self {
+                Self::Hyderabad => Some(format!("{}/holidays.json", HOLIDAYS_BASE_URL)),
+                _ => None,
+            },
+            bundled_holidays: match self {
+                Self::Hyderabad => Some(include_str!("../holidays.json")),
+                _ => None,
+            },
+            calendar_name,
+        }
+    }
+}
+impl CampusProfile {
+    /// Converts a local date and wall clock time on campus to utc.
+    pub fn to_utc(&self, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+        Some(
+            self.timezone
+                .from_local_datetime(&date.and_time(time))
+                .single()?
+                .with_timezone(&Utc),
+        )
+    }
+    /// The campus-local date of a point in time.
+    pub fn local_date(&self, date_time: &DateTime<Utc>) -> NaiveDate {
+        date_time.with_timezone(&self.timezone).date_naive()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CampusError(String);
+impl fmt::Display for CampusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown campus \"{}\", expected one of pilani, goa, hyderabad, dubai",
+            self.0
+        )
+    }
+}
+impl std::error::Error for CampusError {}
+impl FromStr for Campus {
+    type Err = CampusError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Campus::ALL
+            .into_iter()
+            .find(|campus| campus.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| CampusError(s.to_string()))
+    }
+}
+impl fmt::Display for Campus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
@@ -0,0 +1,683 @@
+use chrono::{NaiveDate, Utc};
+use chrono_to_ics::api::cache::Cache;
+use chrono_to_ics::api::client::{ApiClient, ApiError};
+use chrono_to_ics::api::data::TimeTable;
+use chrono_to_ics::api::holidays::acad_year_label;
+use chrono_to_ics::api::holidays::saved_override;
+use chrono_to_ics::api::holidays::HolidayData;
+use chrono_to_ics::api::http::{Http, HttpOptions, CHRONO_API_URL};
+use chrono_to_ics::api::link::{parse_timetable_id, LinkError};
+use chrono_to_ics::api::responses::AcademicCalendarResponse;
+use chrono_to_ics::api::snapshot::{snapshot_path, Snapshot};
+use chrono_to_ics::api::validate::{validate_calendar, Severity};
+use chrono_to_ics::caldav::{CalDav, CalDavError};
+use chrono_to_ics::campus::Campus;
+use chrono_to_ics::converter::{ConvertError, Converter};
+use chrono_to_ics::diff::TimeTableDiff;
+use chrono_to_ics::ics::{self, CalendarOptions};
+use chrono_to_ics::import::{self, ImportError, ImportRules};
+use chrono_to_ics::merge;
+use chrono_to_ics::schedule::Occurrence;
+use chrono_to_ics::server::Server;
+use chrono_to_ics::watch::{write_atomic, Update, Watcher};
+use clap::{Parser, Subcommand};
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Convert chrono timetables to ics. Starts the gui when no command is given.
+#[derive(Parser, Debug)]
+#[command(name = "chrono-to-ics", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// campus whose holidays, timezone and slot timings are used
+    #[arg(long, global = true, default_value_t = Campus::default())]
+    pub campus: Campus,
+    /// ask the server for fresh data instead of using cached responses
+    #[arg(long, global = true)]
+    pub refresh: bool,
+    /// hours the cached course catalog and holidays are used before revalidating
+    #[arg(long, global = true, value_name = "HOURS", default_value_t = 24)]
+    pub cache_ttl: u64,
+    /// seconds a request may take before it is retried
+    #[arg(long, global = true, value_name = "SECONDS", default_value_t = 30)]
+    pub timeout: u64,
+    /// times a request is retried after a timeout or server error
+    #[arg(long, global = true, default_value_t = 2)]
+    pub retries: u32,
+    /// chrono api to fetch the catalog and timetables from, e.g. a local stand-in
+    #[arg(long, global = true, value_name = "URL", default_value = CHRONO_API_URL)]
+    pub api_url: String,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Convert a timetable link (or bare id) to an ics file
+    Convert {
+        /// share, view or edit link of the timetable, or its id
+        link: String,
+        #[arg(short, long, default_value = "timetable.ics")]
+        output: PathBuf,
+        /// keep classes on restricted holidays, except the ones given with --take-restricted
+        #[arg(long)]
+        skip_restricted: bool,
+        /// name of a restricted holiday you take, can be repeated
+        #[arg(long, value_name = "NAME")]
+        take_restricted: Vec<String>,
+        /// also save the linked timetable as json, to edit it and run render on it
+        #[arg(long, value_name = "FILE")]
+        json: Option<PathBuf>,
+    },
+    /// Keep fetching a timetable and rewrite the ics whenever it or the holidays change
+    Watch {
+        /// share, view or edit link of the timetable, or its id
+        link: String,
+        #[arg(short, long, default_value = "timetable.ics")]
+        output: PathBuf,
+        /// minutes between fetches
+        #[arg(long, value_name = "MINUTES", default_value_t = 30)]
+        interval: u64,
+        /// every write is appended here with what changed
+        #[arg(long, value_name = "FILE", default_value = "timetable_changes.log")]
+        log: PathBuf,
+        /// keep classes on restricted holidays, except the ones given with --take-restricted
+        #[arg(long)]
+        skip_restricted: bool,
+        /// name of a restricted holiday you take, can be repeated
+        #[arg(long, value_name = "NAME")]
+        take_restricted: Vec<String>,
+    },
+    /// Serve timetables as calendar feeds at /calendar/<timetable id>.ics to subscribe to
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+        /// minutes a calendar is served before its timetable is fetched again
+        #[arg(long, value_name = "MINUTES", default_value_t = 15)]
+        max_age: u64,
+        /// read holiday data from this file instead of downloading it
+        #[arg(long, value_name = "FILE")]
+        holidays: Option<PathBuf>,
+    },
+    /// Push the events of a timetable to a CalDAV calendar, updating and deleting old ones
+    Push {
+        /// share, view or edit link of the timetable, or its id
+        link: String,
+        /// url of the calendar collection
+        #[arg(long, value_name = "URL")]
+        collection: String,
+        /// username, the password is read from the CALDAV_PASSWORD environment variable
+        #[arg(long)]
+        user: Option<String>,
+    },
+    /// Add the events of a timetable to your own ics file, replacing the ones added before
+    Merge {
+        /// share, view or edit link of the timetable, or its id
+        link: String,
+        /// calendar the events are merged into, created when missing
+        #[arg(long, value_name = "FILE")]
+        into: PathBuf,
+    },
+    /// Write the ics of a timetable saved with convert --json
+    Render {
+        file: PathBuf,
+        #[arg(short, long, default_value = "timetable.ics")]
+        output: PathBuf,
+    },
+    /// Print what changed in a timetable saved with convert --json
+    Diff {
+        /// the timetable as saved before
+        previous: PathBuf,
+        /// the timetable now, fetched again when missing
+        current: Option<PathBuf>,
+        /// print the changes as json
+        #[arg(long)]
+        json: bool,
+    },
+    /// Add a semester to a holiday file from the academic calendar published as ics or csv
+    Import {
+        /// academic calendar, read as csv when the extension is .csv and as ics otherwise
+        file: PathBuf,
+        /// holiday file the semester is added to, created when missing
+        #[arg(short, long, default_value = "holidays.json")]
+        output: PathBuf,
+        /// academic year the calendar is for, e.g. 2024 for 2024-25, guessed when missing
+        #[arg(long)]
+        acad_year: Option<i32>,
+        /// semester number, guessed from the start of classwork when missing
+        #[arg(long)]
+        semester: Option<u8>,
+    },
+    /// Check a holiday file for invalid dates, duplicates and other mistakes
+    Validate {
+        #[arg(default_value = "holidays.json")]
+        file: PathBuf,
+    },
+    /// Print today's classes from the timetable saved by the last convert
+    Today {
+        #[arg(long, default_value_os_t = snapshot_path())]
+        snapshot: PathBuf,
+    },
+    /// Print the next class, its room and how long until it starts
+    Next {
+        #[arg(long, default_value_os_t = snapshot_path())]
+        snapshot: PathBuf,
+    },
+    /// Print the classes of this week
+    Week {
+        #[arg(long, default_value_os_t = snapshot_path())]
+        snapshot: PathBuf,
+    },
+}
+
+pub fn run(cli: Cli) -> Result<(), CliError> {
+    let (cache, http) = network(&cli);
+    match cli.command {
+        Some(Command::Convert {
+            link,
+            output,
+            skip_restricted,
+            take_restricted,
+            json,
+        }) => {
+            // taking some restricted holidays implies skipping the others
+            let restricted =
+                (skip_restricted || !take_restricted.is_empty()).then_some(take_restricted);
+            convert(&link, &output, json, cli.campus, cache, http, restricted)
+        }
+        Some(Command::Watch {
+            link,
+            output,
+            interval,
+            log,
+            skip_restricted,
+            take_restricted,
+        }) => {
+            let restricted =
+                (skip_restricted || !take_restricted.is_empty()).then_some(take_restricted);
+            let id = parse_timetable_id(&link).map_err(CliError::InvalidLink)?;
+            let api = fetch(id, cli.campus, cache, http)?;
+            let interval = Duration::from_secs(interval.max(1) * 60);
+            watch(api, output, log, interval, restricted)
+        }
+        Some(Command::Serve {
+            addr,
+            max_age,
+            holidays,
+        }) => {
+            let mut options = CalendarOptions::new().campus(cli.campus);
+            if let Some(holidays) = holidays {
+                options = options.holidays(HolidayData::File(holidays));
+            }
+            let converter = Converter::with_http(options, cache, http)
+                .map_err(|e| CliError::Serve(e.to_string()))?;
+            serve(
+                &addr,
+                converter,
+                Duration::from_secs(max_age.saturating_mul(60)),
+            )
+        }
+        Some(Command::Push {
+            link,
+            collection,
+            user,
+        }) => push(&link, &collection, user, cli.campus, cache, http),
+        Some(Command::Merge { link, into }) => merge_into(&link, &into, cli.campus, cache, http),
+        Some(Command::Diff {
+            previous,
+            current,
+            json,
+        }) => diff(&previous, current.as_ref(), json, cache, http),
+        Some(Command::Import {
+            file,
+            output,
+            acad_year,
+            semester,
+        }) => import_calendar(&file, &output, acad_year, semester, cli.campus),
+        Some(Command::Render { file, output }) => render(&file, &output),
+        Some(Command::Validate { file }) => validate_holidays(&file),
+        Some(Command::Today { snapshot }) => today(&snapshot),
+        Some(Command::Next { snapshot }) => next(&snapshot),
+        Some(Command::Week { snapshot }) => week(&snapshot),
+        None => Ok(()),
+    }
+}
+
+fn network(cli: &Cli) -> (Cache, HttpOptions) {
+    let cache = Cache {
+        ttl: Duration::from_secs(cli.cache_ttl.saturating_mul(60 * 60)),
+        refresh: cli.refresh,
+        ..Default::default()
+    };
+    let http = HttpOptions {
+        timeout: Duration::from_secs(cli.timeout),
+        retries: cli.retries,
+        api_url: cli.api_url.clone(),
+        ..Default::default()
+    };
+    (cache, http)
+}
+
+fn fetch(
+    id: String,
+    campus: Campus,
+    cache: Cache,
+    http: HttpOptions,
+) -> Result<ApiClient, CliError> {
+    let http = Http::new(http).map_err(|e| CliError::Fetch(e.into()))?;
+    let mut api = ApiClient::with_timetable(id, campus, cache, http).map_err(CliError::Fetch)?;
+    api.update_time_table()
+        .ok_or(CliError::InvalidTimeTableData)?;
+    Ok(api)
+}
+
+fn convert(
+    link: &str,
+    output: &PathBuf,
+    json: Option<PathBuf>,
+    campus: Campus,
+    cache: Cache,
+    http: HttpOptions,
+    taken_restricted: Option<Vec<String>>,
+) -> Result<(), CliError> {
+    let id = parse_timetable_id(link).map_err(CliError::InvalidLink)?;
+    let mut api = fetch(id, campus, cache, http)?;
+    // the timetable converted last time, to report what changed since
+    let path = snapshot_path();
+    let previous = Snapshot::load(&path)
+        .ok()
+        .and_then(|snapshot| snapshot.time_table(None))
+        .filter(|previous| api.timetable.as_ref().is_some_and(|t| t.id == previous.id));
+    if let (Some(previous), Some(current)) = (&previous, &api.timetable) {
+        let diff = TimeTableDiff::new(previous, current);
+        if !diff.is_empty() {
+            println!("changed since the last convert:\n{}", diff);
+        }
+    }
+    // saved so today, next and week work without network
+    if let Some(mut snapshot) = api.snapshot() {
+        snapshot.taken_restricted = taken_restricted.clone();
+        if snapshot.save(&path).is_err() {
+            eprintln!("warning: unable to save {}", path.display());
+        }
+    }
+    let timetable = api.timetable.as_mut().unwrap();
+    if let Some(taken) = taken_restricted {
+        timetable.keep_restricted_holidays(&taken);
+    }
+    timetable
+        .warnings
+        .iter()
+        .for_each(|warning| eprintln!("warning: {}", warning));
+    if let Some(json) = json {
+        timetable
+            .save(&json)
+            .map_err(|_| CliError::UnableToWriteData)?;
+        println!("timetable saved to {}", json.display());
+    }
+    let calendar = ics::make_calendar_string(timetable);
+    std::fs::write(output, calendar).map_err(|_| CliError::UnableToWriteData)?;
+    if let Some(source) = api.holiday_source() {
+        eprintln!("{}", source);
+    }
+    if api.offline() {
+        eprintln!("warning: server unreachable, used cached data");
+    }
+    println!("saved to {}", output.display());
+    Ok(())
+}
+
+fn watch(
+    api: ApiClient,
+    output: PathBuf,
+    log: PathBuf,
+    interval: Duration,
+    taken_restricted: Option<Vec<String>>,
+) -> Result<(), CliError> {
+    let options = CalendarOptions::new().campus(api.campus());
+    let mut watcher = Watcher::new(api, &output, options)
+        .log(log)
+        .taken_restricted(taken_restricted);
+    println!(
+        "checking every {} minutes, stop with ctrl-c",
+        interval.as_secs() / 60
+    );
+    watcher.run(interval, |update| {
+        let now = Utc::now().format("%H:%M");
+        match update {
+            Ok(Update::Unchanged) => {}
+            Ok(Update::Written(None)) => println!("{} saved to {}", now, output.display()),
+            Ok(Update::Written(Some(changes))) => {
+                println!(
+                    "{} changed, saved to {}:\n{}",
+                    now,
+                    output.display(),
+                    changes
+                )
+            }
+            // the next check tries again
+            Err(e) => eprintln!("{} warning: {}", now, e),
+        }
+    })
+}
+
+fn serve(addr: &str, converter: Converter, max_age: Duration) -> Result<(), CliError> {
+    let server = Server::bind(addr, converter)
+        .map_err(|e| CliError::Serve(e.to_string()))?
+        .max_age(max_age);
+    let addr = server
+        .local_addr()
+        .map_or(addr.to_string(), |addr| addr.to_string());
+    println!(
+        "subscribe to webcal://{}/calendar/<timetable id>.ics, stop with ctrl-c",
+        addr
+    );
+    server.run();
+    Ok(())
+}
+
+fn push(
+    link: &str,
+    collection: &str,
+    user: Option<String>,
+    campus: Campus,
+    cache: Cache,
+    http: HttpOptions,
+) -> Result<(), CliError> {
+    let caldav = CalDav::new(
+        Http::new(http.clone()).map_err(|e| CliError::Fetch(e.into()))?,
+        collection,
+    )
+    .map_err(CliError::CalDav)?;
+    let caldav = match user {
+        Some(user) => {
+            caldav.credentials(user, std::env::var("CALDAV_PASSWORD").unwrap_or_default())
+        }
+        None => caldav,
+    };
+    let converter = Converter::with_http(CalendarOptions::new().campus(campus), cache, http)
+        .map_err(CliError::Convert)?;
+    let conversion = converter.convert(link).map_err(CliError::Convert)?;
+    conversion
+        .diagnostics
+        .iter()
+        .for_each(|diagnostic| eprintln!("{}", diagnostic));
+    let report = caldav
+        .push(&conversion.time_table, converter.options())
+        .map_err(CliError::CalDav)?;
+    println!("{}", report);
+    report
+        .conflicts
+        .iter()
+        .for_each(|uid| eprintln!("warning: {} changed on the server, left as it is", uid));
+    Ok(())
+}
+
+fn merge_into(
+    link: &str,
+    into: &PathBuf,
+    campus: Campus,
+    cache: Cache,
+    http: HttpOptions,
+) -> Result<(), CliError> {
+    let id = parse_timetable_id(link).map_err(CliError::InvalidLink)?;
+    let api = fetch(id, campus, cache, http)?;
+    let time_table = api
+        .timetable
+        .as_ref()
+        .ok_or(CliError::InvalidTimeTableData)?;
+    let calendar = ics::make_calendar(time_table);
+    if !into.exists() {
+        write_atomic(into, &calendar.to_string()).map_err(|_| CliError::UnableToWriteData)?;
+        println!("saved to {}", into.display());
+        return Ok(());
+    }
+    let existing = std::fs::read_to_string(into).map_err(|_| CliError::UnableToReadData)?;
+    let merged = merge::merge(&existing, &time_table.id, &calendar)
+        .map_err(|e| CliError::InvalidCalendarFile(e.to_string()))?;
+    // a crash while writing must not cost the user their calendar
+    write_atomic(into, &merged.ics).map_err(|_| CliError::UnableToWriteData)?;
+    println!(
+        "replaced {} generated events with {} in {}",
+        merged.removed,
+        merged.added,
+        into.display()
+    );
+    Ok(())
+}
+
+fn render(file: &PathBuf, output: &PathBuf) -> Result<(), CliError> {
+    let time_table =
+        TimeTable::load(file).map_err(|e| CliError::InvalidTimeTableFile(e.to_string()))?;
+    std::fs::write(output, ics::make_calendar_string(&time_table))
+        .map_err(|_| CliError::UnableToWriteData)?;
+    println!("saved to {}", output.display());
+    Ok(())
+}
+
+fn diff(
+    previous: &PathBuf,
+    current: Option<&PathBuf>,
+    json: bool,
+    cache: Cache,
+    http: HttpOptions,
+) -> Result<(), CliError> {
+    let previous =
+        TimeTable::load(previous).map_err(|e| CliError::InvalidTimeTableFile(e.to_string()))?;
+    let current = match current {
+        Some(current) => {
+            TimeTable::load(current).map_err(|e| CliError::InvalidTimeTableFile(e.to_string()))?
+        }
+        None => fetch(previous.id.clone(), previous.campus, cache, http)?
+            .timetable
+            .ok_or(CliError::InvalidTimeTableData)?,
+    };
+    let diff = TimeTableDiff::new(&previous, &current);
+    match json {
+        true => println!(
+            "{}",
+            diff.to_json().map_err(|_| CliError::UnableToWriteData)?
+        ),
+        false => println!("{}", diff),
+    }
+    Ok(())
+}
+
+fn import_calendar(
+    file: &PathBuf,
+    output: &PathBuf,
+    acad_year: Option<i32>,
+    semester: Option<u8>,
+    campus: Campus,
+) -> Result<(), CliError> {
+    let profile = campus.profile();
+    let data = std::fs::read_to_string(file).map_err(|_| CliError::UnableToReadData)?;
+    let entries = match file.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => import::read_csv(&data),
+        _ => import::read_ics(&data, &profile),
+    }
+    .map_err(CliError::Import)?;
+    let mut holidays =
+        import::import(&entries, &ImportRules::default(), &profile).map_err(CliError::Import)?;
+    holidays.acad_year = acad_year.unwrap_or(holidays.acad_year);
+    holidays.semester = semester.unwrap_or(holidays.semester);
+
+    let mut academic_calendar = match output.exists() {
+        true => serde_json::from_str::<AcademicCalendarResponse>(
+            &std::fs::read_to_string(output).map_err(|_| CliError::UnableToReadData)?,
+        )
+        .map_err(|_| CliError::InvalidHolidayData)?,
+        false => AcademicCalendarResponse::default(),
+    };
+    println!(
+        "semester {} of {}: {} holidays, {} day swaps",
+        holidays.semester,
+        acad_year_label(holidays.acad_year),
+        holidays.holidays.len(),
+        holidays.time_table_changes.len()
+    );
+    let previous = academic_calendar.clone();
+    academic_calendar.upsert(holidays);
+    if academic_calendar == previous {
+        println!(
+            "{} is already up to date (version {})",
+            output.display(),
+            academic_calendar.version
+        );
+        return Ok(());
+    }
+    let issues = validate_calendar(&academic_calendar);
+    issues.iter().for_each(|issue| println!("{}", issue));
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(CliError::InvalidHolidayFile(format!(
+            "{} errors, {} was left as it is",
+            errors,
+            output.display()
+        )));
+    }
+    academic_calendar.version += 1;
+    let json = serde_json::to_string_pretty(&academic_calendar)
+        .map_err(|_| CliError::InvalidHolidayData)?;
+    write_atomic(output, &json).map_err(|_| CliError::UnableToWriteData)?;
+    println!(
+        "saved to {} (version {})",
+        output.display(),
+        academic_calendar.version
+    );
+    Ok(())
+}
+
+fn validate_holidays(file: &PathBuf) -> Result<(), CliError> {
+    let data = std::fs::read_to_string(file).map_err(|_| CliError::UnableToReadData)?;
+    let academic_calendar = serde_json::from_str::<AcademicCalendarResponse>(&data)
+        .map_err(|e| CliError::InvalidHolidayFile(e.to_string()))?;
+    let issues = validate_calendar(&academic_calendar);
+    issues.iter().for_each(|issue| println!("{}", issue));
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    match errors {
+        0 => {
+            println!("{} is valid", file.display());
+            Ok(())
+        }
+        _ => Err(CliError::InvalidHolidayFile(format!("{} errors", errors))),
+    }
+}
+
+fn load_snapshot(path: &PathBuf) -> Result<TimeTable, CliError> {
+    let snapshot = Snapshot::load(path).map_err(|_| CliError::NoSnapshot(path.clone()))?;
+    let (holiday_override, warning) = saved_override();
+    if let Some(warning) = warning {
+        eprintln!("warning: {}", warning);
+    }
+    snapshot
+        .time_table(holiday_override.as_ref())
+        .ok_or(CliError::InvalidTimeTableData)
+}
+
+// 09:00-09:50 CS F111 lecture in F102
+fn describe(time_table: &TimeTable, occurrence: &Occurrence) -> String {
+    let timezone = time_table.campus.profile().timezone;
+    format!(
+        "{}-{} {} {} in {}",
+        occurrence.start.with_timezone(&timezone).format("%H:%M"),
+        occurrence.end.with_timezone(&timezone).format("%H:%M"),
+        occurrence.course.code,
+        occurrence.kind,
+        occurrence.room
+    )
+}
+
+fn today(snapshot: &PathBuf) -> Result<(), CliError> {
+    let time_table = load_snapshot(snapshot)?;
+    let today = time_table.campus.profile().local_date(&Utc::now());
+    let classes = time_table.day(today);
+    if classes.is_empty() {
+        println!("no classes today");
+    }
+    for occurrence in classes.iter() {
+        println!("{}", describe(&time_table, occurrence));
+    }
+    Ok(())
+}
+
+fn next(snapshot: &PathBuf) -> Result<(), CliError> {
+    let time_table = load_snapshot(snapshot)?;
+    let now = Utc::now();
+    let Some(occurrence) = time_table.next_class(now) else {
+        println!("no more classes this semester");
+        return Ok(());
+    };
+    let minutes = (occurrence.start - now).num_minutes();
+    let until = match (minutes / (24 * 60), minutes / 60 % 24, minutes % 60) {
+        (0, 0, minutes) => format!("{}m", minutes),
+        (0, hours, minutes) => format!("{}h {}m", hours, minutes),
+        (days, hours, _) => format!("{}d {}h", days, hours),
+    };
+    println!("{} (in {})", describe(&time_table, &occurrence), until);
+    Ok(())
+}
+
+fn week(snapshot: &PathBuf) -> Result<(), CliError> {
+    let time_table = load_snapshot(snapshot)?;
+    let today = time_table.campus.profile().local_date(&Utc::now());
+    let mut day: Option<NaiveDate> = None;
+    for occurrence in time_table.week(today).iter() {
+        if day != Some(occurrence.date) {
+            day = Some(occurrence.date);
+            println!("{}", occurrence.date.format("%a %d %b"));
+        }
+        println!("  {}", describe(&time_table, occurrence));
+    }
+    if day.is_none() {
+        println!("no classes this week");
+    }
+    Ok(())
+}
+
+pub enum CliError {
+    InvalidLink(LinkError),
+    Fetch(ApiError),
+    InvalidTimeTableData,
+    InvalidTimeTableFile(String),
+    InvalidHolidayData,
+    InvalidHolidayFile(String),
+    InvalidCalendarFile(String),
+    Import(ImportError),
+    UnableToReadData,
+    UnableToWriteData,
+    NoSnapshot(PathBuf),
+    Serve(String),
+    Convert(ConvertError),
+    CalDav(CalDavError),
+}
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidLink(e) => write!(f, "your link is invalid: {}", e),
+            Self::Fetch(e) => write!(f, "unable to fetch data: {}", e),
+            Self::InvalidTimeTableData => write!(f, "timetable is invalid"),
+            Self::InvalidTimeTableFile(e) => write!(f, "timetable file is invalid: {}", e),
+            Self::InvalidHolidayData => write!(f, "holiday data is invalid"),
+            Self::InvalidHolidayFile(e) => write!(f, "holiday file is invalid: {}", e),
+            Self::InvalidCalendarFile(e) => write!(f, "calendar file is invalid: {}", e),
+            Self::Import(e) => write!(f, "{}", e),
+            Self::UnableToReadData => write!(f, "unable to read data"),
+            Self::UnableToWriteData => write!(f, "unable to write data"),
+            Self::NoSnapshot(path) => write!(
+                f,
+                "no saved timetable at {}, run convert first",
+                path.display()
+            ),
+            Self::Serve(e) => write!(f, "{}", e),
+            Self::Convert(e) => write!(f, "{}", e),
+            Self::CalDav(e) => write!(f, "unable to push to the calendar: {}", e),
+        }
+    }
+}
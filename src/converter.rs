@@ -0,0 +1,147 @@
+//! Timetable link in, calendar out: fetching, linking and ics generation in one call.
+use crate::api::cache::Cache;
+use crate::api::catalog::CourseCatalog;
+use crate::api::client::{ApiClient, ApiError};
+use crate::api::data::TimeTable;
+use crate::api::holidays::{saved_override, HolidayOverride};
+use crate::api::http::{Http, HttpOptions};
+use crate::api::link::{parse_timetable_id, LinkError};
+use crate::ics::{self, CalendarOptions};
+use icalendar::Calendar;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::Instant;
+use std::{fmt, io};
+
+/// A converted timetable.
+#[derive(Debug)]
+pub struct Conversion {
+    pub calendar: Calendar,
+    pub time_table: TimeTable,
+    /// where the holiday data came from, stale data used while offline and the
+    /// warnings of the timetable
+    pub diagnostics: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ConvertError {
+    InvalidLink(LinkError),
+    Fetch(ApiError),
+    /// the bundled or local holiday data asked for could not be read
+    Holidays(io::Error),
+    InvalidTimeTableData,
+}
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLink(e) => write!(f, "your link is invalid: {}", e),
+            Self::Fetch(e) => write!(f, "unable to fetch data: {}", e),
+            Self::Holidays(e) => write!(f, "unable to read holiday data: {}", e),
+            Self::InvalidTimeTableData => write!(f, "timetable is invalid"),
+        }
+    }
+}
+impl std::error::Error for ConvertError {}
+
+/// Converts timetables with one set of [`CalendarOptions`]. The http client and the course
+/// catalog are shared by every conversion, so a converter can be kept around and used from
+/// several threads. The catalog is fetched again once it is older than the cache ttl.
+#[derive(Debug)]
+pub struct Converter {
+    options: CalendarOptions,
+    cache: Cache,
+    http: Http,
+    /// with the time it was fetched
+    catalog: Mutex<Option<(Arc<CourseCatalog>, Instant)>>,
+    /// with the warning of reading it, the saved one is read on the first conversion
+    holiday_override: OnceLock<(Option<HolidayOverride>, Option<String>)>,
+}
+impl Converter {
+    pub fn new(options: CalendarOptions) -> Result<Self, ConvertError> {
+        Self::with_http(options, Cache::default(), HttpOptions::default())
+    }
+    pub fn with_http(
+        options: CalendarOptions,
+        cache: Cache,
+        http: HttpOptions,
+    ) -> Result<Self, ConvertError> {
+        Ok(Self {
+            options,
+            cache,
+            http: Http::new(http).map_err(|e| ConvertError::Fetch(e.into()))?,
+            catalog: Mutex::new(None),
+            holiday_override: OnceLock::new(),
+        })
+    }
+    /// Applies `holiday_override` instead of the one saved by the holiday editor.
+    pub fn holiday_override(mut self, holiday_override: Option<HolidayOverride>) -> Self {
+        self.holiday_override = OnceLock::from((holiday_override, None));
+        self
+    }
+    pub fn options(&self) -> &CalendarOptions {
+        &self.options
+    }
+    /// Converts a timetable link or bare id.
+    pub fn convert(&self, link: &str) -> Result<Conversion, ConvertError> {
+        let id = parse_timetable_id(link).map_err(ConvertError::InvalidLink)?;
+        let academic_calendar = self
+            .options
+            .holidays
+            .load_local(self.options.campus)
+            .transpose()
+            .map_err(ConvertError::Holidays)?;
+        let (holiday_override, override_warning) =
+            self.holiday_override.get_or_init(saved_override);
+        let catalog = self.catalog();
+        let mut api = ApiClient::with_data(
+            id,
+            self.options.campus,
+            self.cache.clone(),
+            self.http.clone(),
+            catalog.clone(),
+            academic_calendar,
+            holiday_override.clone(),
+        )
+        .map_err(ConvertError::Fetch)?;
+        // a catalog fetched by this conversion starts a new ttl
+        if let Some(fetched) = api.catalog() {
+            if !catalog.is_some_and(|kept| Arc::ptr_eq(&kept, &fetched)) {
+                *self.catalog.lock().unwrap_or_else(PoisonError::into_inner) =
+                    Some((fetched, Instant::now()));
+            }
+        }
+
+        api.update_time_table();
+        let mut time_table = api
+            .timetable
+            .take()
+            .ok_or(ConvertError::InvalidTimeTableData)?;
+        time_table.warnings.extend(override_warning.clone());
+        let mut diagnostics = api
+            .holiday_source()
+            .map(|source| source.to_string())
+            .into_iter()
+            .collect::<Vec<String>>();
+        if api.offline() {
+            diagnostics.push("server unreachable, used cached data".to_string());
+        }
+        diagnostics.extend(time_table.warnings.iter().cloned());
+        Ok(Conversion {
+            calendar: ics::calendar(&time_table, &self.options),
+            time_table,
+            diagnostics,
+        })
+    }
+    /// Drops the course catalog kept from earlier conversions, the next one fetches it again.
+    pub fn refresh_catalog(&self) {
+        *self.catalog.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+    /// The kept catalog while it is younger than the cache ttl.
+    fn catalog(&self) -> Option<Arc<CourseCatalog>> {
+        self.catalog
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .filter(|(_, fetched)| fetched.elapsed() < self.cache.ttl)
+            .map(|(catalog, _)| catalog.clone())
+    }
+}
@@ -0,0 +1,478 @@
+//! What changed between two versions of a timetable, e.g. the saved one and a fresh fetch.
+use crate::api::data::{Course, ExamKind, Section, SectionKind, TimeTable, Timing};
+use chrono::{DateTime, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::fmt;
+
+type ExamTime = Option<(DateTime<Utc>, DateTime<Utc>)>;
+
+/// One change, sections are identified by course code and kind.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    CourseAdded {
+        code: String,
+        name: String,
+    },
+    CourseRemoved {
+        code: String,
+        name: String,
+    },
+    SectionAdded {
+        code: String,
+        kind: SectionKind,
+        number: i32,
+    },
+    SectionRemoved {
+        code: String,
+        kind: SectionKind,
+        number: i32,
+    },
+    /// switched to another section of the same kind
+    SectionChanged {
+        code: String,
+        kind: SectionKind,
+        from: i32,
+        to: i32,
+    },
+    /// the section no longer meets at the `removed` timings and now meets at the `added` ones
+    SectionMoved {
+        code: String,
+        kind: SectionKind,
+        number: i32,
+        removed: Vec<Timing>,
+        added: Vec<Timing>,
+    },
+    /// same day and slots, another classroom
+    RoomChanged {
+        code: String,
+        kind: SectionKind,
+        number: i32,
+        day: Weekday,
+        start: u8,
+        end: u8,
+        from: String,
+        to: String,
+    },
+    InstructorsChanged {
+        code: String,
+        kind: SectionKind,
+        number: i32,
+        removed: Vec<String>,
+        added: Vec<String>,
+    },
+    ExamChanged {
+        code: String,
+        exam: ExamKind,
+        from: ExamTime,
+        to: ExamTime,
+    },
+}
+impl Change {
+    /// One line of text, exam times in `timezone`.
+    pub fn describe(&self, timezone: Tz) -> String {
+        match self {
+            Self::CourseAdded { code, name } => format!("{} {} added", code, name),
+            Self::CourseRemoved { code, name } => format!("{} {} removed", code, name),
+            Self::SectionAdded { code, kind, number } => {
+                format!("{} {} {} added", code, kind, number)
+            }
+            Self::SectionRemoved { code, kind, number } => {
+                format!("{} {} {} removed", code, kind, number)
+            }
+            Self::SectionChanged {
+                code,
+                kind,
+                from,
+                to,
+            } => format!("{} {} changed from section {} to {}", code, kind, from, to),
+            Self::SectionMoved {
+                code,
+                kind,
+                number,
+                removed,
+                added,
+            } => format!(
+                "{} {} {} moved from {} to {}",
+                code,
+                kind,
+                number,
+                timings(removed),
+                timings(added)
+            ),
+            Self::RoomChanged {
+                code,
+                kind,
+                number,
+                day,
+                start,
+                end,
+                from,
+                to,
+            } => format!(
+                "{} {} {} on {} moved from {} to {}",
+                code,
+                kind,
+                number,
+                slots(*day, *start, *end),
+                from,
+                to
+            ),
+            Self::InstructorsChanged {
+                code,
+                kind,
+                number,
+                removed,
+                added,
+            } => {
+                let instructors = removed
+                    .iter()
+                    .map(|name| format!("-{}", name))
+                    .chain(added.iter().map(|name| format!("+{}", name)))
+                    .collect::<Vec<String>>();
+                format!(
+                    "{} {} {} instructors: {}",
+                    code,
+                    kind,
+                    number,
+                    instructors.join(", ")
+                )
+            }
+            Self::ExamChanged {
+                code,
+                exam,
+                from,
+                to,
+            } => {
+                let time = |time: &ExamTime| match time {
+                    Some((start, end)) => format!(
+                        "{}-{}",
+                        start.with_timezone(&timezone).format("%a %d %b %H:%M"),
+                        end.with_timezone(&timezone).format("%H:%M")
+                    ),
+                    None => "none".to_string(),
+                };
+                format!(
+                    "{} {} moved from {} to {}",
+                    code,
+                    exam,
+                    time(from),
+                    time(to)
+                )
+            }
+        }
+    }
+}
+
+// Mon slots 2-3 in F102
+fn timings(timings: &[Timing]) -> String {
+    match timings {
+        [] => "none".to_string(),
+        timings => timings
+            .iter()
+            .map(|timing| {
+                format!(
+                    "{} in {}",
+                    slots(timing.day, timing.start, timing.end),
+                    timing.classroom
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", "),
+    }
+}
+fn slots(day: Weekday, start: u8, end: u8) -> String {
+    match start == end {
+        true => format!("{} slot {}", day, start),
+        false => format!("{} slots {}-{}", day, start, end),
+    }
+}
+
+/// Changes from a previous version of a timetable to the current one. Holidays and day
+/// swaps are left out, they come from the holiday data and not the timetable.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimeTableDiff {
+    #[serde(skip)]
+    timezone: Tz,
+    pub changes: Vec<Change>,
+}
+impl TimeTableDiff {
+    /// Courses are matched by code, sections by kind.
+    pub fn new(previous: &TimeTable, current: &TimeTable) -> Self {
+        let mut changes: Vec<Change> = previous
+            .courses
+            .iter()
+            .filter(|course| !current.courses.iter().any(|c| c.code == course.code))
+            .map(|course| Change::CourseRemoved {
+                code: course.code.clone(),
+                name: course.name.clone(),
+            })
+            .collect();
+        for course in current.courses.iter() {
+            match previous.courses.iter().find(|c| c.code == course.code) {
+                Some(previous) => diff_course(previous, course, &mut changes),
+                None => changes.push(Change::CourseAdded {
+                    code: course.code.clone(),
+                    name: course.name.clone(),
+                }),
+            }
+        }
+        Self {
+            timezone: current.campus.profile().timezone,
+            changes,
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+    /// `{"changes": [{"change": "room_changed", "code": ..., ...}]}`, times in utc.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+impl fmt::Display for TimeTableDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return write!(f, "no changes");
+        }
+        let lines = self
+            .changes
+            .iter()
+            .map(|change| change.describe(self.timezone))
+            .collect::<Vec<String>>();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+fn diff_course(previous: &Course, current: &Course, changes: &mut Vec<Change>) {
+    let code = &current.code;
+    for kind in [
+        SectionKind::Lecture,
+        SectionKind::Tutorial,
+        SectionKind::Lab,
+    ] {
+        match (section(previous, kind), section(current, kind)) {
+            (None, None) => {}
+            (None, Some(section)) => changes.push(Change::SectionAdded {
+                code: code.clone(),
+                kind,
+                number: section.number,
+            }),
+            (Some(section), None) => changes.push(Change::SectionRemoved {
+                code: code.clone(),
+                kind,
+                number: section.number,
+            }),
+            // a different section has different timings and rooms anyway
+            (Some(previous), Some(current)) if previous.number != current.number => {
+                changes.push(Change::SectionChanged {
+                    code: code.clone(),
+                    kind,
+                    from: previous.number,
+                    to: current.number,
+                })
+            }
+            (Some(previous), Some(current)) => diff_section(code, kind, previous, current, changes),
+        }
+    }
+    for (exam, from, to) in [
+        (
+            ExamKind::Midsem,
+            previous.midsem_date_time,
+            current.midsem_date_time,
+        ),
+        (
+            ExamKind::Compre,
+            previous.compre_date_time,
+            current.compre_date_time,
+        ),
+    ] {
+        if from != to {
+            changes.push(Change::ExamChanged {
+                code: code.clone(),
+                exam,
+                from,
+                to,
+            });
+        }
+    }
+}
+
+fn section(course: &Course, kind: SectionKind) -> Option<&Section> {
+    course
+        .sections()
+        .find(|(section_kind, _)| *section_kind == kind)
+        .map(|(_, section)| section)
+}
+
+fn diff_section(
+    code: &str,
+    kind: SectionKind,
+    previous: &Section,
+    current: &Section,
+    changes: &mut Vec<Change>,
+) {
+    let slot = |timing: &Timing| (timing.day, timing.start, timing.end);
+    let missing = |timings: &[Timing], from: &[Timing]| {
+        timings
+            .iter()
+            .filter(|timing| !from.iter().any(|other| slot(other) == slot(timing)))
+            .cloned()
+            .collect::<Vec<Timing>>()
+    };
+    let removed = missing(&previous.timings, &current.timings);
+    let added = missing(&current.timings, &previous.timings);
+    if !removed.is_empty() || !added.is_empty() {
+        changes.push(Change::SectionMoved {
+            code: code.to_string(),
+            kind,
+            number: current.number,
+            removed,
+            added,
+        });
+    }
+    for timing in current.timings.iter() {
+        if let Some(before) = previous
+            .timings
+            .iter()
+            .find(|before| slot(before) == slot(timing) && before.classroom != timing.classroom)
+        {
+            changes.push(Change::RoomChanged {
+                code: code.to_string(),
+                kind,
+                number: current.number,
+                day: timing.day,
+                start: timing.start,
+                end: timing.end,
+                from: before.classroom.clone(),
+                to: timing.classroom.clone(),
+            });
+        }
+    }
+    let removed = missing_names(&previous.instructors, &current.instructors);
+    let added = missing_names(&current.instructors, &previous.instructors);
+    if !removed.is_empty() || !added.is_empty() {
+        changes.push(Change::InstructorsChanged {
+            code: code.to_string(),
+            kind,
+            number: current.number,
+            removed,
+            added,
+        });
+    }
+}
+fn missing_names(names: &[String], from: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .filter(|name| !from.contains(name))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::campus::Campus;
+    use chrono::{NaiveDate, TimeZone};
+
+    fn time_table(course: Course) -> TimeTable {
+        TimeTable::builder(
+            Campus::Hyderabad,
+            NaiveDate::from_ymd_opt(2024, 8, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 11, 30).unwrap(),
+        )
+        .course(course)
+        .build()
+        .unwrap()
+    }
+    fn lecture(section: Section) -> TimeTable {
+        time_table(
+            Course::builder("CS F111", "Computer Programming")
+                .lecture(section)
+                .build(),
+        )
+    }
+
+    #[test]
+    fn section_moved() {
+        let previous = lecture(Section::new(1).timing(Timing::new(Weekday::Mon, "F102", 2, 2)));
+        let current = lecture(Section::new(1).timing(Timing::new(Weekday::Tue, "F102", 2, 2)));
+        assert_eq!(
+            TimeTableDiff::new(&previous, &current).changes,
+            vec![Change::SectionMoved {
+                code: "CS F111".to_string(),
+                kind: SectionKind::Lecture,
+                number: 1,
+                removed: vec![Timing::new(Weekday::Mon, "F102", 2, 2)],
+                added: vec![Timing::new(Weekday::Tue, "F102", 2, 2)],
+            }]
+        );
+    }
+
+    #[test]
+    fn room_changed() {
+        let previous = lecture(Section::new(1).timing(Timing::new(Weekday::Mon, "F102", 2, 3)));
+        let current = lecture(Section::new(1).timing(Timing::new(Weekday::Mon, "F105", 2, 3)));
+        let diff = TimeTableDiff::new(&previous, &current);
+        assert_eq!(
+            diff.changes,
+            vec![Change::RoomChanged {
+                code: "CS F111".to_string(),
+                kind: SectionKind::Lecture,
+                number: 1,
+                day: Weekday::Mon,
+                start: 2,
+                end: 3,
+                from: "F102".to_string(),
+                to: "F105".to_string(),
+            }]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "CS F111 lecture 1 on Mon slots 2-3 moved from F102 to F105"
+        );
+    }
+
+    #[test]
+    fn instructors_changed() {
+        let previous = lecture(Section::new(1).instructor("A").instructor("B"));
+        let current = lecture(Section::new(1).instructor("B").instructor("C"));
+        assert_eq!(
+            TimeTableDiff::new(&previous, &current).changes,
+            vec![Change::InstructorsChanged {
+                code: "CS F111".to_string(),
+                kind: SectionKind::Lecture,
+                number: 1,
+                removed: vec!["A".to_string()],
+                added: vec!["C".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn exam_changed() {
+        let at = |day, hour| Utc.with_ymd_and_hms(2024, 10, day, hour, 0, 0).unwrap();
+        let course = |day| {
+            Course::builder("CS F111", "Computer Programming")
+                .midsem(at(day, 4), at(day, 5))
+                .build()
+        };
+        let diff = TimeTableDiff::new(&time_table(course(7)), &time_table(course(8)));
+        assert_eq!(
+            diff.changes,
+            vec![Change::ExamChanged {
+                code: "CS F111".to_string(),
+                exam: ExamKind::Midsem,
+                from: Some((at(7, 4), at(7, 5))),
+                to: Some((at(8, 4), at(8, 5))),
+            }]
+        );
+    }
+
+    #[test]
+    fn unchanged() {
+        let time_table = lecture(Section::new(1).timing(Timing::new(Weekday::Mon, "F102", 2, 2)));
+        assert!(TimeTableDiff::new(&time_table, &time_table).is_empty());
+    }
+}
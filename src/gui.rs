@@ -0,0 +1,231 @@
+use crate::holiday_editor::HolidayEditor;
+use chrono_to_ics::api::client::ApiClient;
+use chrono_to_ics::api::link::{parse_timetable_id, LinkError};
+use chrono_to_ics::api::snapshot::{snapshot_path, Snapshot};
+use chrono_to_ics::campus::Campus;
+use chrono_to_ics::diff::TimeTableDiff;
+use chrono_to_ics::ics;
+use eframe::egui;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::prelude::*;
+
+/// changes since the last run, as json
+const CHANGES_FILE: &str = "timetable_changes.json";
+
+pub fn run() -> Result<(), eframe::Error> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([250.0, 125.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        "Chrono to ics",
+        options,
+        Box::new(|_cc| Box::<Gui>::default()),
+    )
+}
+#[derive(Default)]
+struct Gui {
+    api_client: Option<ApiClient>,
+    link: String,
+    campus: Campus,
+    skip_restricted: bool,
+    window_info: String,
+    window_open: bool,
+    holiday_editor: Option<HolidayEditor>,
+}
+
+impl eframe::App for Gui {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.window_open {
+            egui::Window::new("Result")
+                // .open(&mut self.window_open)
+                .show(ctx, |ui| {
+                    ui.label(self.window_info.as_str());
+                    if ui.button("ok").clicked() {
+                        self.window_open = false;
+                    }
+                });
+        }
+        if let Some(editor) = self.holiday_editor.as_mut() {
+            let mut open = true;
+            let mut saved = None;
+            egui::Window::new("Holidays")
+                .open(&mut open)
+                .show(ctx, |ui| saved = editor.show(ui));
+            if let (Some(holiday_override), Some(api)) = (saved, self.api_client.as_mut()) {
+                api.holiday_override = Some(holiday_override);
+            }
+            if !open {
+                self.holiday_editor = None;
+            }
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Chrono to ics");
+
+                let name_label = ui.label("Your link: ");
+                ui.text_edit_singleline(&mut self.link)
+                    .labelled_by(name_label.id);
+
+                egui::ComboBox::from_label("campus")
+                    .selected_text(self.campus.name())
+                    .show_ui(ui, |ui| {
+                        for campus in Campus::ALL {
+                            ui.selectable_value(&mut self.campus, campus, campus.name());
+                        }
+                    });
+
+                ui.checkbox(&mut self.skip_restricted, "skip restricted holidays");
+
+                ui.add_space(10.0);
+                if ui.button("run").clicked() {
+                    self.window_info = match self.run() {
+                        Ok(holiday_source) => {
+                            format!("success saved to timetable.ics\n{}", holiday_source)
+                        }
+                        Err(e) => e.to_string(),
+                    };
+                    self.window_open = true;
+                }
+                if ui.button("edit holidays").clicked() {
+                    if let Err(e) = self.open_holiday_editor() {
+                        self.window_info = e.to_string();
+                        self.window_open = true;
+                    } else {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(
+                            520.0, 480.0,
+                        )));
+                    }
+                }
+            });
+        });
+    }
+}
+impl Gui {
+    fn run(&mut self) -> Result<String, GuiError> {
+        let id = parse_timetable_id(&self.link).map_err(GuiError::InvalidLink)?;
+        let skip_restricted = self.skip_restricted;
+        let api = self.api_client(id)?;
+
+        match api.fetch_timetable() {
+            Ok(_) => {}
+            Err(_) => {
+                return Err(GuiError::UnableToFetchTimetable);
+            }
+        }
+        match api.update_time_table() {
+            Some(_) => {}
+            None => {
+                return Err(GuiError::InvalidTimeTableData);
+            }
+        }
+        let snapshot_path = snapshot_path();
+        let diff = Snapshot::load(&snapshot_path)
+            .ok()
+            .and_then(|snapshot| snapshot.time_table(None))
+            .zip(api.timetable.as_ref())
+            .filter(|(previous, current)| previous.id == current.id)
+            .map(|(previous, current)| TimeTableDiff::new(&previous, current))
+            .filter(|diff| !diff.is_empty());
+        // kept next to the snapshot the changes are measured against
+        let changes_path = snapshot_path.with_file_name(CHANGES_FILE);
+        let changes_saved = match diff.as_ref().map(|diff| diff.to_json()) {
+            Some(Ok(json)) => std::fs::write(&changes_path, json).map_err(|e| e.to_string()),
+            Some(Err(e)) => Err(e.to_string()),
+            None => Ok(()),
+        };
+        if let Some(mut snapshot) = api.snapshot() {
+            snapshot.taken_restricted = skip_restricted.then(Vec::new);
+            // the command line queries still work when this fails
+            let _ = snapshot.save(&snapshot_path);
+        }
+        if skip_restricted {
+            api.timetable
+                .as_mut()
+                .unwrap()
+                .keep_restricted_holidays(&[]);
+        }
+        match write_to_file(&ics::make_calendar_string(api.timetable.as_ref().unwrap())) {
+            Some(_) => (),
+            None => {
+                return Err(GuiError::UnableToWriteData);
+            }
+        };
+
+        Ok(api
+            .holiday_source()
+            .map(|source| source.to_string())
+            .into_iter()
+            .chain(api.timetable.as_ref().unwrap().warnings.iter().cloned())
+            .chain(diff.map(|diff| match &changes_saved {
+                Ok(_) => format!(
+                    "changed since the last run (saved to {}):\n{}",
+                    changes_path.display(),
+                    diff
+                ),
+                Err(e) => format!(
+                    "changed since the last run (unable to save to {}: {}):\n{}",
+                    changes_path.display(),
+                    e,
+                    diff
+                ),
+            }))
+            .collect::<Vec<String>>()
+            .join("\n"))
+    }
+    fn api_client(&mut self, id: String) -> Result<&mut ApiClient, GuiError> {
+        if let Some(client) = self.api_client.as_mut() {
+            client.id = id;
+            client
+                .set_campus(self.campus)
+                .map_err(|_| GuiError::UnableToFetchCourseData)?;
+        } else {
+            self.api_client = Some(
+                ApiClient::with_campus(id, self.campus)
+                    .map_err(|_| GuiError::UnableToFetchCourseData)?,
+            );
+        }
+        Ok(self.api_client.as_mut().unwrap())
+    }
+    fn open_holiday_editor(&mut self) -> Result<(), GuiError> {
+        let id = self
+            .api_client
+            .as_ref()
+            .map(|api| api.id.clone())
+            .unwrap_or_default();
+        let api = self.api_client(id)?;
+        let remote = api.holidays().ok_or(GuiError::UnableToFetchCourseData)?;
+        self.holiday_editor = Some(HolidayEditor::new(
+            remote,
+            api.holiday_override.as_ref(),
+            api.campus(),
+        ));
+        Ok(())
+    }
+}
+
+fn write_to_file(data: &String) -> Option<()> {
+    let mut file = File::create("timetable.ics").unwrap();
+    file.write_all(data.as_bytes()).unwrap();
+    Some(())
+}
+
+enum GuiError {
+    InvalidLink(LinkError),
+    UnableToFetchTimetable,
+    UnableToFetchCourseData,
+    UnableToWriteData,
+    InvalidTimeTableData,
+}
+impl Display for GuiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidLink(e) => write!(f, "your link is invalid: {}", e),
+            Self::UnableToFetchTimetable => write!(f, "unable to fetch the timetable"),
+            Self::UnableToFetchCourseData => write!(f, "unable to access internet"),
+            Self::UnableToWriteData => write!(f, "unable to write data"),
+            Self::InvalidTimeTableData => write!(f, "timetable is invalid"),
+        }
+    }
+}
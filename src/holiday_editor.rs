@@ -0,0 +1,185 @@
+use chrono_to_ics::api::holidays::{normalize_date, override_path, HolidayOverride};
+use chrono_to_ics::api::responses::{HolidayResponse, HolidayString, TimeTableChangeResponse};
+use chrono_to_ics::campus::{Campus, CampusProfile};
+use eframe::egui;
+
+const DAY_CODES: [&str; 5] = ["M", "T", "W", "Th", "F"];
+const HOLIDAY_KINDS: [&str; 3] = ["gazetted", "restricted", "institute"];
+
+/// Edits a copy of the remote holiday data and saves the difference as the local override.
+pub struct HolidayEditor {
+    remote: HolidayResponse,
+    edited: HolidayResponse,
+    profile: CampusProfile,
+    status: String,
+}
+
+impl HolidayEditor {
+    pub fn new(
+        remote: &HolidayResponse,
+        holiday_override: Option<&HolidayOverride>,
+        campus: Campus,
+    ) -> Self {
+        Self {
+            remote: remote.clone(),
+            edited: match holiday_override {
+                Some(holiday_override) => holiday_override.apply(remote),
+                None => remote.clone(),
+            },
+            profile: campus.profile(),
+            status: String::new(),
+        }
+    }
+    /// Draws the editor, returns the new override once the user saved it.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<HolidayOverride> {
+        let mut saved = None;
+        egui::Grid::new("semester_dates").show(ui, |ui| {
+            date_row(ui, "classwork start", &mut self.edited.classwork_start);
+            date_row(ui, "classwork end", &mut self.edited.classwork_end);
+            date_row(ui, "midsem start", &mut self.edited.midsem_start);
+            date_row(ui, "midsem end", &mut self.edited.midsem_end);
+        });
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+                ui.heading("Holidays");
+                let mut removed = None;
+                egui::Grid::new("holidays").striped(true).show(ui, |ui| {
+                    for (i, holiday) in self.edited.holidays.iter_mut().enumerate() {
+                        ui.text_edit_singleline(&mut holiday.name);
+                        ui.text_edit_singleline(&mut holiday.date);
+                        egui::ComboBox::from_id_source(("kind", i))
+                            .selected_text(holiday.kind.as_deref().unwrap_or("institute"))
+                            .show_ui(ui, |ui| {
+                                for kind in HOLIDAY_KINDS {
+                                    ui.selectable_value(
+                                        &mut holiday.kind,
+                                        Some(kind.to_string()),
+                                        kind,
+                                    );
+                                }
+                            });
+                        // empty means the whole day
+                        let mut closure = holiday.closure.clone().unwrap_or_default();
+                        if ui
+                            .add(
+                                egui::TextEdit::singleline(&mut closure)
+                                    .hint_text("full day")
+                                    .desired_width(80.0),
+                            )
+                            .changed()
+                        {
+                            holiday.closure = Some(closure).filter(|c| !c.trim().is_empty());
+                        }
+                        if ui.button("delete").clicked() {
+                            removed = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(i) = removed {
+                    self.edited.holidays.remove(i);
+                }
+                if ui.button("add holiday").clicked() {
+                    self.edited.holidays.push(HolidayString::default());
+                }
+
+                ui.heading("Day swaps");
+                let mut removed = None;
+                egui::Grid::new("time_table_changes")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, change) in self.edited.time_table_changes.iter_mut().enumerate() {
+                            ui.text_edit_singleline(&mut change.date);
+                            egui::ComboBox::from_id_source(("day", i))
+                                .selected_text(format!("follow {}", change.day))
+                                .show_ui(ui, |ui| {
+                                    for code in DAY_CODES {
+                                        ui.selectable_value(
+                                            &mut change.day,
+                                            code.to_string(),
+                                            code,
+                                        );
+                                    }
+                                });
+                            if ui.button("delete").clicked() {
+                                removed = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if let Some(i) = removed {
+                    self.edited.time_table_changes.remove(i);
+                }
+                if ui.button("add day swap").clicked() {
+                    self.edited
+                        .time_table_changes
+                        .push(TimeTableChangeResponse {
+                            date: String::new(),
+                            day: DAY_CODES[0].to_string(),
+                        });
+                }
+            });
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("save").clicked() {
+                saved = self.save();
+            }
+            if ui.button("reset to remote").clicked() {
+                self.edited = self.remote.clone();
+            }
+        });
+        ui.label(self.status.as_str());
+        saved
+    }
+    fn save(&mut self) -> Option<HolidayOverride> {
+        let mut edited = self.edited.clone();
+        // midsem is optional, left out when both of its dates are empty
+        let midsem =
+            !(edited.midsem_start.trim().is_empty() && edited.midsem_end.trim().is_empty());
+        if !midsem {
+            edited.midsem_start.clear();
+            edited.midsem_end.clear();
+        }
+        let dates = [&mut edited.classwork_start, &mut edited.classwork_end]
+            .into_iter()
+            .chain(
+                [&mut edited.midsem_start, &mut edited.midsem_end]
+                    .into_iter()
+                    .filter(|_| midsem),
+            )
+            .chain(edited.holidays.iter_mut().map(|holiday| &mut holiday.date))
+            .chain(
+                edited
+                    .time_table_changes
+                    .iter_mut()
+                    .map(|change| &mut change.date),
+            );
+        for date in dates {
+            match normalize_date(date, &self.profile) {
+                Some(normalized) => *date = normalized,
+                None => {
+                    self.status = format!("invalid date \"{}\", use YYYY-MM-DD", date);
+                    return None;
+                }
+            }
+        }
+        let holiday_override = HolidayOverride::between(&self.remote, &edited);
+        let path = override_path();
+        // an override that isn't on disk must not be applied either
+        if let Err(e) = holiday_override.save(&path) {
+            self.status = format!("unable to save: {}", e);
+            return None;
+        }
+        self.status = format!("saved to {}", path.display());
+        self.edited = edited;
+        Some(holiday_override)
+    }
+}
+
+fn date_row(ui: &mut egui::Ui, label: &str, date: &mut String) {
+    ui.label(label);
+    ui.text_edit_singleline(date);
+    ui.end_row();
+}
@@ -1,7 +1,161 @@
-use crate::api::data::{Section, TimeTable, Timing};
-use chrono::{DateTime, Datelike, Days, Duration, Timelike, Utc, Weekday};
-use icalendar::{Calendar, Component, Event, EventLike, Property};
+use crate::api::data::{Course, Holiday, HolidayKind, Section, SectionKind, TimeTable, Timing};
+use crate::api::holidays::HolidayData;
+use crate::campus::{Campus, CampusProfile};
+use chrono::{DateTime, Datelike, Days, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use icalendar::{Alarm, Calendar, Component, Event, EventLike, Property};
+use std::collections::HashSet;
 const UTC_DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Summaries of the generated events. `{name}`, `{code}`, `{section}` and `{kind}` are
+/// replaced by the course name, course code, section number and section kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SummaryTemplates {
+    pub lecture: String,
+    pub tutorial: String,
+    pub lab: String,
+    pub exam: String,
+}
+impl Default for SummaryTemplates {
+    fn default() -> Self {
+        Self {
+            lecture: "Lec:{name} ".to_string(),
+            tutorial: "Tut:{name} ".to_string(),
+            lab: "Lab: {name} ".to_string(),
+            exam: "Exam: {name}".to_string(),
+        }
+    }
+}
+impl SummaryTemplates {
+    fn class(&self, kind: SectionKind) -> &str {
+        match kind {
+            SectionKind::Lecture => &self.lecture,
+            SectionKind::Tutorial => &self.tutorial,
+            SectionKind::Lab => &self.lab,
+        }
+    }
+}
+fn fill(
+    template: &str,
+    course: &Course,
+    kind: Option<SectionKind>,
+    section: Option<&Section>,
+) -> String {
+    template
+        .replace("{name}", &course.name)
+        .replace("{code}", &course.code)
+        .replace(
+            "{section}",
+            &section.map_or(String::new(), |section| section.number.to_string()),
+        )
+        .replace(
+            "{kind}",
+            &kind.map_or("exam".to_string(), |kind| kind.to_string()),
+        )
+}
+
+/// How classes are written to the calendar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Expansion {
+    /// one weekly event per timing, holidays as exceptions and day swaps as extra dates
+    #[default]
+    Recurring,
+    /// one event per class, for calendars that handle rrules badly
+    Expanded,
+}
+
+/// What goes into the generated calendar. Built with chained setters:
+/// `CalendarOptions::new().campus(Campus::Goa).reminder(Duration::minutes(10))`.
+#[derive(Debug, Clone)]
+pub struct CalendarOptions {
+    pub(crate) campus: Campus,
+    pub(crate) calendar_name: Option<String>,
+    pub(crate) timezone: Option<Tz>,
+    pub(crate) summaries: SummaryTemplates,
+    pub(crate) reminders: Vec<Duration>,
+    pub(crate) kinds: Vec<SectionKind>,
+    pub(crate) exams: bool,
+    pub(crate) transparent: bool,
+    pub(crate) expansion: Expansion,
+    pub(crate) holidays: HolidayData,
+}
+impl Default for CalendarOptions {
+    fn default() -> Self {
+        Self {
+            campus: Campus::default(),
+            calendar_name: None,
+            timezone: None,
+            summaries: SummaryTemplates::default(),
+            reminders: vec![],
+            kinds: vec![
+                SectionKind::Lecture,
+                SectionKind::Tutorial,
+                SectionKind::Lab,
+            ],
+            exams: true,
+            transparent: true,
+            expansion: Expansion::default(),
+            holidays: HolidayData::default(),
+        }
+    }
+}
+impl CalendarOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// campus whose holidays and slot timings are used, hyderabad by default
+    pub fn campus(mut self, campus: Campus) -> Self {
+        self.campus = campus;
+        self
+    }
+    /// defaults to the campus calendar name
+    pub fn calendar_name(mut self, name: impl Into<String>) -> Self {
+        self.calendar_name = Some(name.into());
+        self
+    }
+    /// timezone calendar apps show the events in (X-WR-TIMEZONE), defaults to the campus
+    /// timezone. Classes still follow the campus clock, an 8:00 class is at 8:00 on campus.
+    pub fn timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = Some(timezone);
+        self
+    }
+    pub fn summaries(mut self, summaries: SummaryTemplates) -> Self {
+        self.summaries = summaries;
+        self
+    }
+    /// adds a reminder this long before every class and exam
+    pub fn reminder(mut self, before: Duration) -> Self {
+        self.reminders.push(before);
+        self
+    }
+    /// section kinds that get events, all of them by default
+    pub fn kinds(mut self, kinds: &[SectionKind]) -> Self {
+        self.kinds = kinds.to_vec();
+        self
+    }
+    pub fn exams(mut self, exams: bool) -> Self {
+        self.exams = exams;
+        self
+    }
+    /// classes don't mark you busy, on by default
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+    pub fn expansion(mut self, expansion: Expansion) -> Self {
+        self.expansion = expansion;
+        self
+    }
+    pub fn holidays(mut self, holidays: HolidayData) -> Self {
+        self.holidays = holidays;
+        self
+    }
+    fn decorate(&self, event: &mut Event, summary: &str) {
+        for before in self.reminders.iter() {
+            event.alarm(Alarm::display(summary, -*before));
+        }
+    }
+}
 #[derive(Debug)]
 struct EventGen {
     summary: String,
@@ -11,6 +165,8 @@ struct EventGen {
     weekdays: Vec<Weekday>,
     recurence_end: DateTime<Utc>,
     exdates: Vec<DateTime<Utc>>,
+    /// swapped days following one of `weekdays`
+    rdates: Vec<DateTime<Utc>>,
     location: String,
 } // he;p
 impl EventGen {
@@ -18,10 +174,18 @@ impl EventGen {
         summary: String,
         description: String,
         section: &Section,
-        sem_start: &DateTime<Utc>,
-        sem_end: &DateTime<Utc>,
-        holidays: &[DateTime<Utc>],
+        time_table: &TimeTable,
+        holidays: &[Holiday],
+        profile: &CampusProfile,
     ) -> Vec<Self> {
+        let (sem_start, sem_end) = (&time_table.classwork_start, &time_table.classwork_end);
+        let classwork = profile.local_date(sem_start)..=profile.local_date(sem_end);
+        let swaps = time_table
+            .time_table_changes
+            .iter()
+            .map(|change| (profile.local_date(&change.date), change.day))
+            .filter(|(date, _)| classwork.contains(date))
+            .collect::<Vec<(NaiveDate, Weekday)>>();
         let mut timing_sets: Vec<Vec<Timing>> = vec![vec![]];
         // make vec<timing> into sets where the both start and end are the same
         section.timings.iter().for_each(|timing| {
@@ -48,35 +212,71 @@ impl EventGen {
                     .iter()
                     .map(|timing| timing.day)
                     .collect::<Vec<Weekday>>();
+                let at_start = |date: &NaiveDate| {
+                    profile.to_utc(*date, profile.slots.start(timing_vec.first()?.start))
+                };
+                // a swapped day loses the classes of its own weekday and has the ones of
+                // the weekday it follows
+                let has = |day: &Weekday| weekday_vec.contains(day);
+                let swapped_away = swaps
+                    .iter()
+                    .filter(|(date, day)| has(&date.weekday()) && !has(day))
+                    .filter_map(|(date, _)| at_start(date))
+                    .collect::<Vec<DateTime<Utc>>>();
+                let swapped_in = swaps
+                    .iter()
+                    .filter(|(date, day)| has(day) && !has(&date.weekday()))
+                    .filter_map(|(date, _)| at_start(date))
+                    .collect::<Vec<DateTime<Utc>>>();
                 Some(EventGen {
                     summary: summary.clone(),
                     description: description.clone(),
-                    start_time: start_time(sem_start, &weekday_vec, timing_vec.first()?.start)?,
-                    end_time: end_time(sem_start, &weekday_vec, timing_vec.first()?.end)?,
+                    start_time: start_time(
+                        sem_start,
+                        &weekday_vec,
+                        timing_vec.first()?.start,
+                        profile,
+                    )?,
+                    end_time: end_time(sem_start, &weekday_vec, timing_vec.first()?.end, profile)?,
                     weekdays: weekday_vec,
-                    recurence_end: *sem_end,
+                    // classwork_end is midnight of the last day with classes, an UNTIL there
+                    // would drop that day's classes, so the recurrence runs to the next midnight
+                    recurence_end: profile
+                        .to_utc(profile.local_date(sem_end).succ_opt()?, NaiveTime::MIN)?,
                     exdates: holidays
                         .iter()
-                        .filter_map(|date_time| {
-                            date_time
-                                .with_hour(1 + timing_vec.first()?.start as u32)?
-                                .checked_add_days(Days::new(1))
+                        .filter(|holiday| {
+                            timing_vec.first().is_some_and(|timing| {
+                                holiday.closure.cancels(timing.start, timing.end)
+                            })
                         })
+                        .filter_map(|holiday| at_start(&profile.local_date(&holiday.date)))
+                        .chain(swapped_away)
                         .collect::<Vec<DateTime<Utc>>>(),
+                    rdates: swapped_in,
                     location: timing_vec.first()?.classroom.clone(),
                 })
             })
             .collect::<Vec<Self>>()
     }
-    fn to_event(&self) -> Event {
-        let exd = self
-            .exdates
-            .iter()
-            .map(|datetime| datetime.format(UTC_DATE_TIME_FORMAT).to_string())
-            .collect::<Vec<String>>()
-            .join(",");
-        //EXDATE must have same time as event start
-        Event::new()
+    fn to_event(&self, options: &CalendarOptions) -> Event {
+        let dates = |dates: &[DateTime<Utc>]| {
+            dates
+                .iter()
+                .map(|datetime| datetime.format(UTC_DATE_TIME_FORMAT).to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        let (exd, rd) = (dates(&self.exdates), dates(&self.rdates));
+        //EXDATE and RDATE must have same time as event start
+        let mut event = Event::new();
+        if !exd.is_empty() {
+            event.add_property("EXDATE", &exd);
+        }
+        if !rd.is_empty() {
+            event.add_property("RDATE", &rd);
+        }
+        let mut event = event
             .summary(&self.summary)
             .description(&self.description)
             .add_property(
@@ -95,7 +295,6 @@ impl EventGen {
                 )
                 .as_str(),
             )
-            .add_property("EXDATE", &exd)
             .add_property(
                 "DTEND",
                 self.end_time
@@ -104,8 +303,16 @@ impl EventGen {
                     .as_str(),
             )
             .add_property("LOCATION", self.location.as_str())
-            .add_property("TRANSP", "TRANSPARENT")
-            .done()
+            .add_property("TRANSP", transparency(options))
+            .done();
+        options.decorate(&mut event, &self.summary);
+        event
+    }
+}
+fn transparency(options: &CalendarOptions) -> &'static str {
+    match options.transparent {
+        true => "TRANSPARENT",
+        false => "OPAQUE",
     }
 }
 fn weekdays_to_string(weekdays: &[Weekday]) -> String {
@@ -127,59 +334,193 @@ fn weekdays_to_string(weekdays: &[Weekday]) -> String {
         .join(",")
 }
 
+// first day on or after the start of the semester that has one of the weekdays
+fn first_class_date(
+    sem_start: &DateTime<Utc>,
+    weekday: &[Weekday],
+    profile: &CampusProfile,
+) -> Option<NaiveDate> {
+    profile
+        .local_date(sem_start)
+        .iter_days()
+        .take(7)
+        .find(|date| weekday.contains(&date.weekday()))
+}
 fn start_time(
     sem_start: &DateTime<Utc>,
     weekday: &[Weekday],
     timing_start: u8,
+    profile: &CampusProfile,
 ) -> Option<DateTime<Utc>> {
-    let mut date = *sem_start;
-    while !weekday.contains(&date.weekday()) {
-        date += Duration::days(1);
-    }
-    // set hours from 1 -> 2:30am ,2 ->3:30 am
-    // 2:30 am UTC = 8 am IST
-    date.with_hour(1 + timing_start as u32)?.with_minute(30)
+    profile.to_utc(
+        first_class_date(sem_start, weekday, profile)?,
+        profile.slots.start(timing_start),
+    )
 }
 fn end_time(
     sem_start: &DateTime<Utc>,
     weekday: &[Weekday],
-    timing_start: u8,
+    timing_end: u8,
+    profile: &CampusProfile,
 ) -> Option<DateTime<Utc>> {
-    let mut date = *sem_start;
-    while !weekday.contains(&date.weekday()) {
-        date += Duration::days(1);
-    }
-    // set hours from 1 -> 3:20am ,2 ->4:20 am
-    // 3:20 am UTC = 8:50 am IST
-
-    date.with_hour(2 + timing_start as u32)?.with_minute(20)
+    profile.to_utc(
+        first_class_date(sem_start, weekday, profile)?,
+        profile.slots.end(timing_end),
+    )
 }
 
 fn generate_exam_event(
     exam_start: &DateTime<Utc>,
     exam_end: &DateTime<Utc>,
     summary: &str,
+    options: &CalendarOptions,
 ) -> Event {
-    Event::new()
+    let mut event = Event::new()
         .summary(summary)
         .starts(*exam_start)
         .ends(*exam_end)
         .description("something ")
-        .done()
+        .done();
+    options.decorate(&mut event, summary);
+    event
+}
+/// What a generated event stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Class(SectionKind),
+    Midsem,
+    Compre,
+}
+/// An event with the course and section it was generated from.
+#[derive(Debug, Clone)]
+pub struct GeneratedEvent {
+    pub event: Event,
+    pub kind: EventKind,
+    pub course_code: String,
+    /// section number, `None` for exams
+    pub section: Option<i32>,
 }
-pub fn make_calendar(time_table: &TimeTable) -> String {
-    let mut calendar = Calendar::new();
-    calendar.append_property(Property::new("NAME", "bphc calendar"));
 
-    let mut holidays = time_table
-        .holidays
-        .iter()
-        .map(|hoilday| hoilday.date)
-        .collect::<Vec<DateTime<Utc>>>();
+/// Ends the uid of every generated event.
+pub const UID_SUFFIX: &str = "@chrono-to-ics";
+/// Id of the timetable an event was generated for. Ids can contain `-`, so the uid alone
+/// can't tell timetable `abc` from `abc-def`.
+pub const TIME_TABLE_PROPERTY: &str = "X-CHRONO-TO-ICS-TIMETABLE";
+/// Whether the event (its ics text) was generated for timetable `time_table_id`. Events from
+/// before [`TIME_TABLE_PROPERTY`] are matched by `uids`, the uids generated for it now.
+pub(crate) fn generated_for(event: &str, time_table_id: &str, uids: &HashSet<String>) -> bool {
+    match property(event, TIME_TABLE_PROPERTY) {
+        Some(id) => id == time_table_id,
+        None => property(event, "UID").is_some_and(|uid| uids.contains(&uid)),
+    }
+}
+/// The same for the same class or exam of a timetable, so a calendar imported or pushed
+/// again updates its events instead of adding copies.
+fn uid(time_table: &TimeTable, course: &Course, key: &str) -> String {
+    format!(
+        "{}-{}-{}{}",
+        time_table.id,
+        course.code.replace(' ', ""),
+        key,
+        UID_SUFFIX
+    )
+}
+
+/// Value of the first `name` property of an ics text, folded lines joined.
+pub(crate) fn property(ics: &str, name: &str) -> Option<String> {
+    ics.replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "")
+        .lines()
+        .find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let key = key.split(';').next()?;
+            key.eq_ignore_ascii_case(name).then(|| value.to_string())
+        })
+}
+
+/// The calendar of `time_table` with the default options.
+pub fn make_calendar(time_table: &TimeTable) -> Calendar {
+    calendar(time_table, &CalendarOptions::default())
+}
+/// [`make_calendar`] serialized to ics.
+pub fn make_calendar_string(time_table: &TimeTable) -> String {
+    make_calendar(time_table).to_string()
+}
+/// The calendar of `time_table` built the way `options` ask for.
+pub fn calendar(time_table: &TimeTable, options: &CalendarOptions) -> Calendar {
+    let profile = time_table.campus.profile();
+    let mut calendar = Calendar::new();
+    calendar.append_property(Property::new(
+        "NAME",
+        options
+            .calendar_name
+            .as_deref()
+            .unwrap_or(profile.calendar_name),
+    ));
+    calendar.append_property(Property::new(
+        "X-WR-TIMEZONE",
+        options.timezone.unwrap_or(profile.timezone).name(),
+    ));
+    for generated in events(time_table, options) {
+        calendar.push(generated.event);
+    }
+    calendar
+}
+/// Every event [`calendar`] would hold, classes first and then exams.
+pub fn events(time_table: &TimeTable, options: &CalendarOptions) -> Vec<GeneratedEvent> {
+    // times are sent in utc, the display timezone doesn't change them
+    let profile = time_table.campus.profile();
+    let mut events = match options.expansion {
+        Expansion::Recurring => recurring_events(time_table, &profile, options),
+        Expansion::Expanded => expanded_events(time_table, options),
+    };
+    if options.exams {
+        for course in &time_table.courses {
+            let summary = fill(&options.summaries.exam, course, None, None);
+            for (kind, exam) in [
+                (EventKind::Midsem, &course.midsem_date_time),
+                (EventKind::Compre, &course.compre_date_time),
+            ] {
+                if let Some((exam_start, exam_end)) = exam {
+                    let mut event = generate_exam_event(exam_start, exam_end, &summary, options);
+                    let key = match kind {
+                        EventKind::Midsem => "midsem",
+                        _ => "compre",
+                    };
+                    event.uid(&uid(time_table, course, key));
+                    events.push(GeneratedEvent {
+                        event,
+                        kind,
+                        course_code: course.code.clone(),
+                        section: None,
+                    });
+                }
+            }
+        }
+    }
+    for generated in &mut events {
+        generated
+            .event
+            .add_property(TIME_TABLE_PROPERTY, time_table.id.as_str());
+    }
+    events
+}
+fn recurring_events(
+    time_table: &TimeTable,
+    profile: &CampusProfile,
+    options: &CalendarOptions,
+) -> Vec<GeneratedEvent> {
+    let mut holidays = time_table.holidays.clone();
 
     if let Some((mut mid_sem_start, mid_sem_end)) = time_table.midsem_dates {
         while mid_sem_start <= mid_sem_end {
-            holidays.push(mid_sem_start);
+            holidays.push(Holiday {
+                name: "midsem".to_string(),
+                date: mid_sem_start,
+                kind: HolidayKind::Institute,
+                ..Default::default()
+            });
             mid_sem_start = match mid_sem_start.checked_add_days(Days::new(1)) {
                 Some(new_mid_sem_date) => new_mid_sem_date,
                 None => {
@@ -188,56 +529,182 @@ pub fn make_calendar(time_table: &TimeTable) -> String {
             };
         }
     }
-    let mut events: Vec<EventGen> = vec![];
+    let mut events: Vec<GeneratedEvent> = vec![];
     for course in &time_table.courses {
-        if let Some(section) = &course.lecture {
-            events.extend(EventGen::new(
-                format!("Lec:{} ", course.name),
-                course.code.clone(),
-                section,
-                &time_table.classwork_start,
-                &time_table.classwork_end,
-                &holidays,
-            ));
-        }
-        if let Some(section) = &course.lab {
-            events.extend(EventGen::new(
-                format!("Lab: {} ", course.name),
-                course.code.clone(),
-                section,
-                &time_table.classwork_start,
-                &time_table.classwork_end,
-                &holidays,
-            ));
-        }
-        if let Some(section) = &course.tutorial {
-            events.extend(EventGen::new(
-                format!("Tut:{} ", course.name),
-                course.code.clone(),
-                section,
-                &time_table.classwork_start,
-                &time_table.classwork_end,
-                &holidays,
-            ));
-        }
-        if let Some((midsem_start, midsem_end)) = &course.midsem_date_time {
-            calendar.push(generate_exam_event(
-                midsem_start,
-                midsem_end,
-                &format!("Exam: {}", course.name),
-            ));
-        }
-        if let Some((compre_start, compre_end)) = &course.compre_date_time {
-            calendar.push(generate_exam_event(
-                compre_start,
-                compre_end,
-                &format!("Exam: {}", course.name),
-            ));
+        for (kind, section) in [
+            (SectionKind::Lecture, &course.lecture),
+            (SectionKind::Lab, &course.lab),
+            (SectionKind::Tutorial, &course.tutorial),
+        ] {
+            let Some(section) = section.as_ref().filter(|_| options.kinds.contains(&kind)) else {
+                continue;
+            };
+            events.extend(
+                EventGen::new(
+                    fill(
+                        options.summaries.class(kind),
+                        course,
+                        Some(kind),
+                        Some(section),
+                    ),
+                    course.code.clone(),
+                    section,
+                    time_table,
+                    &holidays,
+                    profile,
+                )
+                .iter()
+                .map(|eventgen| {
+                    let mut event = eventgen.to_event(options);
+                    let key = format!(
+                        "{}{}-{}",
+                        kind,
+                        section.number,
+                        eventgen.start_time.format(UTC_DATE_TIME_FORMAT)
+                    );
+                    event.uid(&uid(time_table, course, &key));
+                    GeneratedEvent {
+                        event,
+                        kind: EventKind::Class(kind),
+                        course_code: course.code.clone(),
+                        section: Some(section.number),
+                    }
+                }),
+            );
         }
     }
+    events
+}
+fn expanded_events(time_table: &TimeTable, options: &CalendarOptions) -> Vec<GeneratedEvent> {
+    time_table
+        .occurrences(NaiveDate::MIN, NaiveDate::MAX)
+        .filter(|occurrence| options.kinds.contains(&occurrence.kind))
+        .map(|occurrence| {
+            let summary = fill(
+                options.summaries.class(occurrence.kind),
+                occurrence.course,
+                Some(occurrence.kind),
+                Some(occurrence.section),
+            );
+            let mut event = Event::new()
+                .summary(&summary)
+                .description(&occurrence.course.code)
+                .starts(occurrence.start)
+                .ends(occurrence.end)
+                .location(occurrence.room)
+                .add_property("TRANSP", transparency(options))
+                .done();
+            options.decorate(&mut event, &summary);
+            let key = format!(
+                "{}{}-{}",
+                occurrence.kind,
+                occurrence.section.number,
+                occurrence.start.format(UTC_DATE_TIME_FORMAT)
+            );
+            event.uid(&uid(time_table, occurrence.course, &key));
+            GeneratedEvent {
+                event,
+                kind: EventKind::Class(occurrence.kind),
+                course_code: occurrence.course.code.clone(),
+                section: Some(occurrence.section.number),
+            }
+        })
+        .collect::<Vec<GeneratedEvent>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data::Timing;
+
+    fn time_table() -> TimeTable {
+        TimeTable::builder(
+            Campus::Hyderabad,
+            NaiveDate::from_ymd_opt(2024, 8, 5).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 8, 16).unwrap(),
+        )
+        .id("42")
+        .course(
+            Course::builder("CS F111", "Computer Programming")
+                .lecture(Section::new(1).timing(Timing::new(Weekday::Mon, "F102", 2, 2)))
+                .build(),
+        )
+        .build()
+        .unwrap()
+    }
+
+    #[test]
+    fn recurring_events_follow_day_swaps() {
+        let time_table = TimeTable::builder(
+            Campus::Hyderabad,
+            NaiveDate::from_ymd_opt(2024, 8, 5).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 8, 16).unwrap(),
+        )
+        .id("42")
+        .course(
+            Course::builder("CS F111", "Computer Programming")
+                .lecture(Section::new(1).timing(Timing::new(Weekday::Mon, "F102", 2, 2)))
+                .tutorial(Section::new(1).timing(Timing::new(Weekday::Wed, "F105", 2, 2)))
+                .build(),
+        )
+        // saturday follows monday, wednesday follows friday
+        .day_swap(NaiveDate::from_ymd_opt(2024, 8, 10).unwrap(), Weekday::Mon)
+        .day_swap(NaiveDate::from_ymd_opt(2024, 8, 14).unwrap(), Weekday::Fri)
+        .build()
+        .unwrap();
+        let expanded = events(
+            &time_table,
+            &CalendarOptions::new().expansion(Expansion::Expanded),
+        )
+        .iter()
+        .filter_map(|generated| property(&generated.event.to_string(), "DTSTART"))
+        .collect::<Vec<String>>();
+        assert!(expanded.contains(&"20240810T033000Z".to_string()));
+        assert!(!expanded.contains(&"20240814T033000Z".to_string()));
+
+        let recurring = events(&time_table, &CalendarOptions::new())
+            .into_iter()
+            .map(|generated| (generated.kind, generated.event.to_string()))
+            .collect::<Vec<(EventKind, String)>>();
+        let [(EventKind::Class(SectionKind::Lecture), lecture), (EventKind::Class(SectionKind::Tutorial), tutorial)] =
+            &recurring[..]
+        else {
+            panic!("{:?}", recurring);
+        };
+        assert_eq!(
+            property(lecture, "RDATE").as_deref(),
+            Some("20240810T033000Z")
+        );
+        assert_eq!(property(lecture, "EXDATE"), None);
+        assert_eq!(
+            property(tutorial, "EXDATE").as_deref(),
+            Some("20240814T033000Z")
+        );
+        assert_eq!(property(tutorial, "RDATE"), None);
+    }
+
+    #[test]
+    fn display_timezone_keeps_the_campus_clock() {
+        let options = CalendarOptions::new().timezone(chrono_tz::America::New_York);
+        let calendar = calendar(&time_table(), &options).to_string();
+        assert!(calendar.contains("X-WR-TIMEZONE:America/New_York"));
+        // slot 2 starts at 9:00 in Hyderabad
+        assert!(calendar.contains("DTSTART:20240805T033000Z"));
+    }
 
-    for eventgen in events {
-        calendar.push(eventgen.to_event());
+    #[test]
+    fn expanded_and_recurring_agree() {
+        let starts = |expansion| {
+            let options = CalendarOptions::new()
+                .timezone(chrono_tz::America::New_York)
+                .expansion(expansion);
+            events(&time_table(), &options)
+                .iter()
+                .filter_map(|generated| property(&generated.event.to_string(), "DTSTART"))
+                .collect::<Vec<String>>()
+        };
+        let expanded = starts(Expansion::Expanded);
+        assert_eq!(expanded, ["20240805T033000Z", "20240812T033000Z"]);
+        assert_eq!(starts(Expansion::Recurring), expanded[..1]);
     }
-    calendar.to_string()
 }
@@ -0,0 +1,572 @@
+//! Builds holiday data from the academic calendar the institute publishes as ics or csv.
+use crate::api::holidays::date_string;
+use crate::api::responses::{HolidayResponse, HolidayString, TimeTableChangeResponse};
+use crate::campus::CampusProfile;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use icalendar::parser::{self, Property};
+use std::fmt;
+
+const CSV_DATE_FORMATS: [&str; 4] = ["%Y-%m-%d", "%d-%m-%Y", "%d/%m/%Y", "%d.%m.%Y"];
+// weekday name, weekday and its code in holidays.json
+const WEEKDAY_NAMES: [(&str, Weekday, &str); 5] = [
+    ("monday", Weekday::Mon, "M"),
+    ("tuesday", Weekday::Tue, "T"),
+    ("wednesday", Weekday::Wed, "W"),
+    ("thursday", Weekday::Thu, "Th"),
+    ("friday", Weekday::Fri, "F"),
+];
+
+/// One entry of the academic calendar, `end` is inclusive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarEntry {
+    pub summary: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    ClassworkStart,
+    ClassworkEnd,
+    Midsem,
+    /// classes follow the timetable of the given weekday
+    DaySwap(Weekday),
+    Holiday,
+    Ignored,
+}
+
+/// Keywords used to classify calendar entries, matched case-insensitively against the summary.
+/// Rules are tried in field order, the first match wins.
+#[derive(Debug, Clone)]
+pub struct ImportRules {
+    /// an entry mentioning one of these and a weekday name is a day swap
+    pub day_swap: Vec<String>,
+    pub classwork_start: Vec<String>,
+    pub classwork_end: Vec<String>,
+    pub midsem: Vec<String>,
+    pub holiday: Vec<String>,
+    /// narrows down the kind of an entry already classified as a holiday
+    pub gazetted: Vec<String>,
+    pub restricted: Vec<String>,
+}
+impl Default for ImportRules {
+    fn default() -> Self {
+        let strings = |keywords: &[&str]| {
+            keywords
+                .iter()
+                .map(|keyword| keyword.to_string())
+                .collect::<Vec<String>>()
+        };
+        Self {
+            day_swap: strings(&["timetable", "time table", "time-table"]),
+            classwork_start: strings(&[
+                "commencement of classes",
+                "classwork begins",
+                "classes begin",
+                "classes commence",
+                "first day of classes",
+                "start of classwork",
+            ]),
+            classwork_end: strings(&[
+                "last day of classes",
+                "classwork ends",
+                "classes end",
+                "last class day",
+                "end of classwork",
+            ]),
+            midsem: strings(&[
+                "mid-semester test",
+                "mid-semester exam",
+                "mid semester test",
+                "mid semester exam",
+                "midsem test",
+                "midsem exam",
+                "mid-sem test",
+                "mid-sem exam",
+            ]),
+            holiday: strings(&["holiday", "(h)", "(r)", "vacation", "break", "no classes"]),
+            gazetted: strings(&["(h)", "gazetted"]),
+            restricted: strings(&["(r)", "restricted"]),
+        }
+    }
+}
+impl ImportRules {
+    /// The holiday kind as written in holidays.json, `None` for institute holidays.
+    pub fn holiday_kind(&self, summary: &str) -> Option<&'static str> {
+        let summary = summary.to_lowercase();
+        let matches = |keywords: &[String]| {
+            keywords
+                .iter()
+                .any(|keyword| summary.contains(keyword.as_str()))
+        };
+        if matches(&self.restricted) {
+            Some("restricted")
+        } else if matches(&self.gazetted) {
+            Some("gazetted")
+        } else {
+            None
+        }
+    }
+    pub fn classify(&self, summary: &str) -> EntryKind {
+        let summary = summary.to_lowercase();
+        let matches = |keywords: &[String]| {
+            keywords
+                .iter()
+                .find_map(|keyword| summary.find(keyword.as_str()))
+        };
+        if let Some(position) = matches(&self.day_swap) {
+            // "saturday: monday's timetable", the weekday right before the keyword is followed
+            if let Some(day) = WEEKDAY_NAMES
+                .iter()
+                .filter_map(|(name, day, _)| Some((summary[..position].rfind(name)?, *day)))
+                .max_by_key(|(found_at, _)| *found_at)
+                .map(|(_, day)| day)
+            {
+                return EntryKind::DaySwap(day);
+            }
+        }
+        if matches(&self.classwork_start).is_some() {
+            EntryKind::ClassworkStart
+        } else if matches(&self.classwork_end).is_some() {
+            EntryKind::ClassworkEnd
+        } else if matches(&self.midsem).is_some() {
+            EntryKind::Midsem
+        } else if matches(&self.holiday).is_some() {
+            EntryKind::Holiday
+        } else {
+            EntryKind::Ignored
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    InvalidIcs(String),
+    /// line number and content of a csv row that could not be read
+    InvalidCsvRow(usize, String),
+    MissingClassworkStart,
+    MissingClassworkEnd,
+}
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidIcs(e) => write!(f, "unable to read the ics file: {}", e),
+            Self::InvalidCsvRow(line, row) => {
+                write!(f, "unable to read line {} of the csv file: {}", line, row)
+            }
+            Self::MissingClassworkStart => {
+                write!(f, "no entry marks the start of classwork")
+            }
+            Self::MissingClassworkEnd => write!(f, "no entry marks the end of classwork"),
+        }
+    }
+}
+impl std::error::Error for ImportError {}
+
+/// Reads every VEVENT of an ics file, converting date times to campus-local dates.
+pub fn read_ics(data: &str, profile: &CampusProfile) -> Result<Vec<CalendarEntry>, ImportError> {
+    let unfolded = parser::unfold(data);
+    let calendar = parser::read_calendar(&unfolded).map_err(ImportError::InvalidIcs)?;
+    Ok(calendar
+        .components
+        .iter()
+        .filter(|component| component.name.as_str() == "VEVENT")
+        .filter_map(|event| {
+            let summary = unescape(event.find_prop("SUMMARY")?.val.as_str());
+            let (start, _) = ics_date(event.find_prop("DTSTART")?, profile)?;
+            let end = match event.find_prop("DTEND") {
+                Some(dtend) => match ics_date(dtend, profile)? {
+                    // DTEND of all day events is exclusive
+                    (end, true) => end.pred_opt()?.max(start),
+                    (end, false) => end,
+                },
+                None => start,
+            };
+            Some(CalendarEntry {
+                summary,
+                start,
+                end,
+            })
+        })
+        .collect::<Vec<CalendarEntry>>())
+}
+
+// returns the campus-local date and whether the value was a plain date
+fn ics_date(property: &Property, profile: &CampusProfile) -> Option<(NaiveDate, bool)> {
+    let value = property.val.as_str();
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return Some((date, true));
+    }
+    let date_time =
+        NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S").ok()?;
+    let timezone = property
+        .params
+        .iter()
+        .find(|param| param.key.as_str().eq_ignore_ascii_case("TZID"))
+        .and_then(|param| param.val.as_ref())
+        .and_then(|tzid| tzid.as_str().trim_matches('"').parse::<Tz>().ok());
+    let date_time = match (value.ends_with('Z'), timezone) {
+        (true, _) => DateTime::<Utc>::from_naive_utc_and_offset(date_time, Utc),
+        (false, Some(timezone)) => timezone
+            .from_local_datetime(&date_time)
+            .earliest()?
+            .with_timezone(&Utc),
+        // floating, or a timezone chrono-tz doesn't know, taken as campus time
+        (false, None) => return Some((date_time.date(), false)),
+    };
+    Some((profile.local_date(&date_time), false))
+}
+
+// undoes the escaping of ics text values: `\\`, `\;`, `\,` and `\n` or `\N` for a newline
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Reads `date,description` or `start date,end date,description` rows.
+/// A header row is skipped, dates can be `YYYY-MM-DD`, `DD-MM-YYYY`, `DD/MM/YYYY` or `DD.MM.YYYY`.
+pub fn read_csv(data: &str) -> Result<Vec<CalendarEntry>, ImportError> {
+    let mut entries: Vec<CalendarEntry> = vec![];
+    for (i, line) in data.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = csv_fields(line);
+        let invalid = || ImportError::InvalidCsvRow(i + 1, line.to_string());
+        let Some(start) = fields.first().and_then(|field| csv_date(field)) else {
+            if i == 0 {
+                continue;
+            }
+            return Err(invalid());
+        };
+        let (end, summary) = match fields.get(1).and_then(|field| csv_date(field)) {
+            Some(end) => (end, fields.get(2)),
+            None => (start, fields.get(1)),
+        };
+        entries.push(CalendarEntry {
+            summary: summary.ok_or_else(invalid)?.to_string(),
+            start,
+            end: end.max(start),
+        });
+    }
+    Ok(entries)
+}
+
+fn csv_date(field: &str) -> Option<NaiveDate> {
+    CSV_DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(field.trim(), format).ok())
+}
+
+// splits a csv row, honouring double quoted fields
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                fields.last_mut().unwrap().push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+        .into_iter()
+        .map(|field| field.trim().to_string())
+        .collect::<Vec<String>>()
+}
+
+/// Classifies the entries and turns them into the holiday data of one semester.
+/// The academic year and semester are guessed from the start of classwork.
+pub fn import(
+    entries: &[CalendarEntry],
+    rules: &ImportRules,
+    profile: &CampusProfile,
+) -> Result<HolidayResponse, ImportError> {
+    let mut classwork_start: Option<NaiveDate> = None;
+    let mut classwork_end: Option<NaiveDate> = None;
+    let mut midsem: Option<(NaiveDate, NaiveDate)> = None;
+    let mut holidays: Vec<HolidayString> = vec![];
+    let mut time_table_changes: Vec<TimeTableChangeResponse> = vec![];
+    for entry in entries {
+        match rules.classify(&entry.summary) {
+            EntryKind::ClassworkStart => {
+                classwork_start = Some(classwork_start.map_or(entry.start, |d| d.min(entry.start)))
+            }
+            EntryKind::ClassworkEnd => {
+                classwork_end = Some(classwork_end.map_or(entry.end, |d| d.max(entry.end)))
+            }
+            EntryKind::Midsem => {
+                midsem = Some(match midsem {
+                    Some((start, end)) => (start.min(entry.start), end.max(entry.end)),
+                    None => (entry.start, entry.end),
+                })
+            }
+            EntryKind::DaySwap(day) => time_table_changes.extend(
+                days(entry)
+                    .filter_map(|date| date_string(date, profile))
+                    .map(|date| TimeTableChangeResponse {
+                        date,
+                        day: day_code(day).to_string(),
+                    }),
+            ),
+            EntryKind::Holiday => holidays.extend(
+                days(entry)
+                    .filter_map(|date| date_string(date, profile))
+                    .map(|date| HolidayString {
+                        name: entry.summary.clone(),
+                        date,
+                        kind: rules.holiday_kind(&entry.summary).map(str::to_string),
+                        closure: None,
+                    }),
+            ),
+            EntryKind::Ignored => {}
+        }
+    }
+    let classwork_start = classwork_start.ok_or(ImportError::MissingClassworkStart)?;
+    let classwork_end = classwork_end.ok_or(ImportError::MissingClassworkEnd)?;
+    // the first semester starts around august, the second one in january
+    let semester: u8 = if classwork_start.month() >= 7 { 1 } else { 2 };
+    let date = |date: NaiveDate| date_string(date, profile).unwrap_or_default();
+    Ok(HolidayResponse {
+        acad_year: classwork_start.year() - (semester as i32 - 1),
+        semester,
+        classwork_start: date(classwork_start),
+        classwork_end: date(classwork_end),
+        midsem_start: midsem.map(|(start, _)| date(start)).unwrap_or_default(),
+        midsem_end: midsem.map(|(_, end)| date(end)).unwrap_or_default(),
+        holidays,
+        time_table_changes,
+    })
+}
+
+fn days(entry: &CalendarEntry) -> impl Iterator<Item = NaiveDate> + '_ {
+    entry
+        .start
+        .iter_days()
+        .take_while(|date| *date <= entry.end)
+}
+
+fn day_code(day: Weekday) -> &'static str {
+    WEEKDAY_NAMES
+        .iter()
+        .find(|(_, weekday, _)| *weekday == day)
+        .map_or("", |(_, _, code)| code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::validate::{validate, Severity};
+    use crate::campus::Campus;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn classifies_entries() {
+        let rules = ImportRules::default();
+        for (summary, kind) in [
+            ("Commencement of Classes", EntryKind::ClassworkStart),
+            ("Last day of classes", EntryKind::ClassworkEnd),
+            ("Mid-Semester Test", EntryKind::Midsem),
+            ("Diwali (H)", EntryKind::Holiday),
+            (
+                "Saturday: Monday's timetable",
+                EntryKind::DaySwap(Weekday::Mon),
+            ),
+            (
+                "Classes as per Friday Time Table",
+                EntryKind::DaySwap(Weekday::Fri),
+            ),
+            // no weekday to follow
+            ("Time table released", EntryKind::Ignored),
+            ("Convocation", EntryKind::Ignored),
+        ] {
+            assert_eq!(rules.classify(summary), kind, "{}", summary);
+        }
+        assert_eq!(rules.holiday_kind("Diwali (H)"), Some("gazetted"));
+        assert_eq!(rules.holiday_kind("Holi (R)"), Some("restricted"));
+        assert_eq!(rules.holiday_kind("Mid-semester break"), None);
+    }
+
+    #[test]
+    fn reads_csv() {
+        let csv = "date,description\n\
+                   2024-08-01,Commencement of classes\n\
+                   \n\
+                   28/10/2024,01/11/2024,\"Diwali break, no classes\"\n\
+                   15.08.2024,\"Independence Day \"\"(H)\"\"\"\n";
+        assert_eq!(
+            read_csv(csv).unwrap(),
+            vec![
+                CalendarEntry {
+                    summary: "Commencement of classes".to_string(),
+                    start: date(8, 1),
+                    end: date(8, 1),
+                },
+                CalendarEntry {
+                    summary: "Diwali break, no classes".to_string(),
+                    start: date(10, 28),
+                    end: date(11, 1),
+                },
+                CalendarEntry {
+                    summary: "Independence Day \"(H)\"".to_string(),
+                    start: date(8, 15),
+                    end: date(8, 15),
+                },
+            ]
+        );
+        assert_eq!(
+            read_csv("2024-08-01,Start\nsoon,End"),
+            Err(ImportError::InvalidCsvRow(2, "soon,End".to_string()))
+        );
+    }
+
+    #[test]
+    fn reads_ics() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\nSUMMARY:Diwali break\\, no classes\\nall\r\n  campuses\r\n\
+DTSTART;VALUE=DATE:20241028\r\nDTEND;VALUE=DATE:20241102\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nSUMMARY:Mid-semester test\r\nDTSTART:20241006T200000Z\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nSUMMARY:Convocation\r\nDTSTART;TZID=America/New_York:20241006T200000\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\nSUMMARY:Open day\r\nDTSTART:20241006T200000\r\nEND:VEVENT\r\n\
+END:VCALENDAR\r\n";
+        let entries = read_ics(ics, &Campus::Hyderabad.profile()).unwrap();
+        let found = entries
+            .iter()
+            .map(|entry| (entry.summary.as_str(), entry.start, entry.end))
+            .collect::<Vec<(&str, NaiveDate, NaiveDate)>>();
+        assert_eq!(
+            found,
+            vec![
+                // DTEND of an all day event is exclusive
+                (
+                    "Diwali break, no classes\nall campuses",
+                    date(10, 28),
+                    date(11, 1)
+                ),
+                // 20:00 utc is past midnight in Hyderabad
+                ("Mid-semester test", date(10, 7), date(10, 7)),
+                ("Convocation", date(10, 7), date(10, 7)),
+                // floating times are campus times
+                ("Open day", date(10, 6), date(10, 6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unescapes_text() {
+        assert_eq!(unescape(r"a\, b\; c\\d\ne\Nf"), "a, b; c\\d\ne\nf");
+        assert_eq!(unescape(r"trailing\"), "trailing\\");
+    }
+
+    fn entry(summary: &str, start: NaiveDate, end: NaiveDate) -> CalendarEntry {
+        CalendarEntry {
+            summary: summary.to_string(),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn builds_the_semester() {
+        let profile = Campus::Hyderabad.profile();
+        let entries = [
+            entry("Commencement of classes", date(8, 1), date(8, 1)),
+            entry("Mid-Semester Test", date(10, 9), date(10, 12)),
+            entry("Mid-Semester Test", date(10, 7), date(10, 8)),
+            entry("Diwali (H)", date(10, 30), date(11, 1)),
+            entry("Saturday: Monday's timetable", date(8, 31), date(8, 31)),
+            entry("Convocation", date(11, 2), date(11, 2)),
+            entry("Last day of classes", date(11, 29), date(11, 29)),
+        ];
+        let holidays = import(&entries, &ImportRules::default(), &profile).unwrap();
+        let day = |month, day| date_string(date(month, day), &profile).unwrap();
+        assert_eq!((holidays.acad_year, holidays.semester), (2024, 1));
+        assert_eq!(holidays.classwork_start, day(8, 1));
+        assert_eq!(holidays.classwork_end, day(11, 29));
+        // both entries make up one midsem window
+        assert_eq!(
+            (holidays.midsem_start.as_str(), holidays.midsem_end.as_str()),
+            (day(10, 7).as_str(), day(10, 12).as_str())
+        );
+        // a range becomes one holiday per day
+        assert_eq!(
+            holidays.holidays,
+            [(10, 30), (10, 31), (11, 1)]
+                .into_iter()
+                .map(|(month, d)| HolidayString {
+                    name: "Diwali (H)".to_string(),
+                    date: day(month, d),
+                    kind: Some("gazetted".to_string()),
+                    closure: None,
+                })
+                .collect::<Vec<HolidayString>>()
+        );
+        assert_eq!(
+            holidays.time_table_changes,
+            vec![TimeTableChangeResponse {
+                date: day(8, 31),
+                day: "M".to_string(),
+            }]
+        );
+        assert!(validate(&holidays)
+            .iter()
+            .all(|issue| issue.severity != Severity::Error));
+    }
+
+    #[test]
+    fn second_semester_belongs_to_the_previous_year() {
+        let entries = [
+            entry("Commencement of classes", date(1, 8), date(1, 8)),
+            entry("Last day of classes", date(4, 29), date(4, 29)),
+        ];
+        let holidays = import(
+            &entries,
+            &ImportRules::default(),
+            &Campus::Hyderabad.profile(),
+        )
+        .unwrap();
+        assert_eq!((holidays.acad_year, holidays.semester), (2023, 2));
+        // midsem is optional
+        assert_eq!(
+            (holidays.midsem_start.as_str(), holidays.midsem_end.as_str()),
+            ("", "")
+        );
+    }
+
+    #[test]
+    fn needs_the_classwork_dates() {
+        let profile = Campus::Hyderabad.profile();
+        let start = entry("Commencement of classes", date(8, 1), date(8, 1));
+        let end = entry("Last day of classes", date(11, 29), date(11, 29));
+        assert_eq!(
+            import(&[end], &ImportRules::default(), &profile),
+            Err(ImportError::MissingClassworkStart)
+        );
+        assert_eq!(
+            import(&[start], &ImportRules::default(), &profile),
+            Err(ImportError::MissingClassworkEnd)
+        );
+    }
+}
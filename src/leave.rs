@@ -0,0 +1,189 @@
+//! Plans personal leave: how many classes of each course a set of days off would cost.
+use crate::api::data::{SectionKind, TimeTable};
+use chrono::NaiveDate;
+use icalendar::{Component, Event, EventLike};
+use std::fmt;
+
+/// Classes of one section over the whole semester and how many the leave misses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attendance {
+    pub code: String,
+    pub name: String,
+    pub kind: SectionKind,
+    pub total: u32,
+    pub missed: u32,
+}
+impl Attendance {
+    /// Attendance left after the leave, in percent.
+    pub fn percentage(&self) -> f64 {
+        match self.total {
+            0 => 100.0,
+            total => (total - self.missed) as f64 * 100.0 / total as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LeavePlan {
+    pub days: Vec<NaiveDate>,
+    /// one entry per course section, in timetable order
+    pub attendance: Vec<Attendance>,
+}
+impl LeavePlan {
+    /// Walks every class of the semester, counting the ones on a leave day.
+    /// Holidays, midsem and day swaps are respected, so leave on a holiday costs nothing.
+    pub fn new(time_table: &TimeTable, days: &[NaiveDate]) -> Self {
+        let mut attendance = time_table
+            .courses
+            .iter()
+            .flat_map(|course| {
+                course.sections().map(|(kind, _)| Attendance {
+                    code: course.code.clone(),
+                    name: course.name.clone(),
+                    kind,
+                    total: 0,
+                    missed: 0,
+                })
+            })
+            .collect::<Vec<Attendance>>();
+        for class in time_table.occurrences(NaiveDate::MIN, NaiveDate::MAX) {
+            if let Some(entry) = attendance
+                .iter_mut()
+                .find(|entry| entry.code == class.course.code && entry.kind == class.kind)
+            {
+                entry.total += 1;
+                if days.contains(&class.date) {
+                    entry.missed += 1;
+                }
+            }
+        }
+        let mut days = days.to_vec();
+        days.sort();
+        days.dedup();
+        Self { days, attendance }
+    }
+    pub fn missed(&self) -> u32 {
+        self.attendance.iter().map(|entry| entry.missed).sum()
+    }
+    /// The leave days as all-day events.
+    pub fn to_events(&self, summary: &str) -> Vec<Event> {
+        self.days
+            .iter()
+            .map(|date| {
+                // DTEND of an all-day event is exclusive
+                Event::new()
+                    .summary(summary)
+                    .starts(*date)
+                    .ends(date.succ_opt().unwrap_or(*date))
+                    .done()
+            })
+            .collect::<Vec<Event>>()
+    }
+}
+impl fmt::Display for LeavePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.attendance {
+            writeln!(
+                f,
+                "{} {}: misses {} of {} classes, {:.1}% attendance",
+                entry.code,
+                entry.kind,
+                entry.missed,
+                entry.total,
+                entry.percentage()
+            )?;
+        }
+        write!(f, "{} classes missed in total", self.missed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data::{Course, Section, Timing};
+    use crate::campus::Campus;
+    use chrono::Weekday;
+    use icalendar::DatePerhapsTime;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 8, day).unwrap()
+    }
+
+    // monday the 5th to friday the 16th, no classes on wednesday the 14th
+    fn time_table() -> TimeTable {
+        TimeTable::builder(Campus::Hyderabad, date(5), date(16))
+            .course(
+                Course::builder("CS F111", "Computer Programming")
+                    .lecture(
+                        Section::new(1)
+                            .timing(Timing::new(Weekday::Mon, "F102", 2, 2))
+                            .timing(Timing::new(Weekday::Wed, "F102", 2, 2)),
+                    )
+                    .tutorial(Section::new(1).timing(Timing::new(Weekday::Fri, "F105", 3, 3)))
+                    .build(),
+            )
+            .course(
+                Course::builder("MATH F111", "Mathematics I")
+                    .lecture(Section::new(2).timing(Timing::new(Weekday::Tue, "F101", 4, 4)))
+                    .build(),
+            )
+            .holiday_on("Janmashtami", date(14))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn counts_missed_and_total_classes() {
+        let plan = LeavePlan::new(&time_table(), &[date(12), date(14), date(13), date(12)]);
+        assert_eq!(
+            plan.attendance
+                .iter()
+                .map(|entry| (entry.code.as_str(), entry.kind, entry.total, entry.missed))
+                .collect::<Vec<_>>(),
+            vec![
+                // leave on the holiday costs nothing
+                ("CS F111", SectionKind::Lecture, 3, 1),
+                ("CS F111", SectionKind::Tutorial, 2, 0),
+                ("MATH F111", SectionKind::Lecture, 2, 1),
+            ]
+        );
+        assert_eq!(plan.missed(), 2);
+        assert_eq!(plan.days, vec![date(12), date(13), date(14)]);
+    }
+
+    #[test]
+    fn percentage() {
+        let plan = LeavePlan::new(&time_table(), &[date(12)]);
+        assert!((plan.attendance[0].percentage() - 200.0 / 3.0).abs() < 1e-9);
+        assert_eq!(plan.attendance[1].percentage(), 100.0);
+        let no_classes = Attendance {
+            total: 0,
+            ..plan.attendance[0].clone()
+        };
+        assert_eq!(no_classes.percentage(), 100.0);
+        assert!(plan
+            .to_string()
+            .starts_with("CS F111 lecture: misses 1 of 3 classes, 66.7% attendance\n"));
+        assert!(plan.to_string().ends_with("1 classes missed in total"));
+    }
+
+    #[test]
+    fn leave_days_as_all_day_events() {
+        let plan = LeavePlan::new(&time_table(), &[date(13), date(12)]);
+        let events = plan.to_events("leave");
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| (event.get_summary(), event.get_start(), event.get_end()))
+                .collect::<Vec<_>>(),
+            [12, 13]
+                .into_iter()
+                .map(|day| (
+                    Some("leave"),
+                    Some(DatePerhapsTime::Date(date(day))),
+                    Some(DatePerhapsTime::Date(date(day + 1)))
+                ))
+                .collect::<Vec<_>>()
+        );
+    }
+}
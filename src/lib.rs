@@ -1,5 +1,29 @@
 pub mod api;
 
+pub mod campus;
+
+#[cfg(feature = "http-blocking")]
+pub mod caldav;
+
+#[cfg(feature = "http-blocking")]
+pub mod converter;
+
+pub mod diff;
+
 pub mod ics;
 
+pub mod import;
+
+pub mod leave;
+
+pub mod merge;
+
+pub mod schedule;
+
+#[cfg(feature = "server")]
+pub mod server;
+
+#[cfg(feature = "http-blocking")]
+pub mod watch;
+
 // mod gui;
@@ -1,128 +1,50 @@
-#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
-use chrono_to_ics::api::client::ApiClient;
-use chrono_to_ics::ics;
-use eframe::egui;
-use std::fmt::Display;
-use std::fs::File;
-use std::io::prelude::*;
-pub fn main() -> Result<(), eframe::Error> {
-    let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([250.0, 125.0]),
-        ..Default::default()
-    };
-    eframe::run_native(
-        "Chrono to ics",
-        options,
-        Box::new(|_cc| Box::<Gui>::default()),
-    )
-}
-#[derive(Default)]
-struct Gui {
-    api_client: Option<ApiClient>,
-    link: String,
-    window_info: String,
-    window_open: bool,
-}
-
-impl eframe::App for Gui {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if self.window_open {
-            egui::Window::new("Result")
-                // .open(&mut self.window_open)
-                .show(ctx, |ui| {
-                    ui.label(self.window_info.as_str());
-                    if ui.button("ok").clicked() {
-                        self.window_open = false;
-                    }
-                });
-        }
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.vertical_centered(|ui| {
-                ui.heading("Chrono to ics");
-
-                let name_label = ui.label("Your link: ");
-                ui.text_edit_singleline(&mut self.link)
-                    .labelled_by(name_label.id);
-
-                ui.add_space(10.0);
-                if ui.button("run").clicked() {
-                    self.window_info = match self.run() {
-                        Ok(_) => "success saved to timetable.ics".to_string(),
-                        Err(e) => e.to_string(),
-                    };
-                    self.window_open = true;
-                }
-            });
-        });
+// hide console window on Windows in release, subcommands attach to the console they run in
+#![cfg_attr(
+    all(not(debug_assertions), feature = "gui"),
+    windows_subsystem = "windows"
+)]
+mod cli;
+#[cfg(feature = "gui")]
+mod gui;
+#[cfg(feature = "gui")]
+mod holiday_editor;
+use clap::Parser;
+pub fn main() {
+    #[cfg(all(windows, not(debug_assertions), feature = "gui"))]
+    if std::env::args_os().len() > 1 {
+        attach_console();
     }
-}
-impl Gui {
-    fn run(&mut self) -> Result<(), GuiError> {
-        let id = get_id_from_link(&self.link).ok_or(GuiError::InvalidLink)?;
-        let api = match self.api_client.as_mut() {
-            Some(client) => {
-                client.id = id;
-                client
-            }
-            None => {
-                self.api_client = Some(match ApiClient::new(id) {
-                    Ok(a) => a,
-                    Err(_) => {
-                        return Err(GuiError::UnableToFetchCourseData);
-                    }
-                });
-                self.api_client.as_mut().unwrap()
-            }
-        };
-
-        match api.fetch_timetable() {
-            Ok(_) => {}
-            Err(_) => {
-                return Err(GuiError::InvalidLink);
-            }
+    let cli = cli::Cli::parse();
+    if cli.command.is_some() {
+        if let Err(e) = cli::run(cli) {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
-        match api.update_time_table() {
-            Some(_) => {}
-            None => {
-                return Err(GuiError::InvalidTimeTableData);
-            }
-        }
-        match write_to_file(&ics::make_calendar(api.timetable.as_ref().unwrap())) {
-            Some(_) => (),
-            None => {
-                return Err(GuiError::UnableToWriteData);
-            }
-        };
-
-        Ok(())
+        return;
+    }
+    #[cfg(feature = "gui")]
+    if let Err(e) = gui::run() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    // built without the gui, there is nothing to start
+    #[cfg(not(feature = "gui"))]
+    {
+        use clap::CommandFactory;
+        let _ = cli::Cli::command().print_help();
     }
 }
 
-fn get_id_from_link(link: &str) -> Option<String> {
-    Some(link.split('/').last()?.to_string())
-}
-
-fn write_to_file(data: &String) -> Option<()> {
-    let mut file = File::create("timetable.ics").unwrap();
-    file.write_all(data.as_bytes()).unwrap();
-    Some(())
-}
-
-enum GuiError {
-    InvalidLink,
-    // UnableToFetchTimetable,
-    UnableToFetchCourseData,
-    UnableToWriteData,
-    InvalidTimeTableData,
-}
-impl Display for GuiError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::InvalidLink => write!(f, "your link is invalid"),
-            // Self::UnableToFetchTimetable => "unable to access internet".to_string(),
-            Self::UnableToFetchCourseData => write!(f, "unable to access internet"),
-            Self::UnableToWriteData => write!(f, "unable to write data"),
-            Self::InvalidTimeTableData => write!(f, "timetable is invalid"),
-        }
+/// Without a console of its own, the cli writes to the one it was started from.
+#[cfg(all(windows, not(debug_assertions), feature = "gui"))]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // fails when started from explorer, there is no console to write to then
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
     }
 }
@@ -0,0 +1,216 @@
+//! Puts the generated events into a calendar file the user keeps for everything else.
+//! Events generated earlier for the same timetable are recognised by their timetable id and
+//! replaced, so merging again doesn't add copies, and the rest of the file is kept line for
+//! line.
+use crate::ics::{generated_for, property};
+use icalendar::Calendar;
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    /// no BEGIN:VCALENDAR ... END:VCALENDAR to merge into
+    NotACalendar,
+}
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotACalendar => write!(f, "not an ics calendar"),
+        }
+    }
+}
+impl std::error::Error for MergeError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merged {
+    pub ics: String,
+    /// generated events taken out of the existing calendar
+    pub removed: usize,
+    pub added: usize,
+}
+
+/// `existing` with the events generated before for timetable `time_table_id` removed and the
+/// events of `generated` added before its END:VCALENDAR. Events of other timetables merged
+/// into the same file are kept, as are its line endings.
+pub fn merge(
+    existing: &str,
+    time_table_id: &str,
+    generated: &Calendar,
+) -> Result<Merged, MergeError> {
+    let generated = generated.to_string();
+    let events = events(&generated);
+    let uids = events
+        .iter()
+        .filter_map(|event| property(&event.join("\n"), "UID"))
+        .collect::<HashSet<String>>();
+    let newline = match existing.contains("\r\n") {
+        true => "\r\n",
+        false => "\n",
+    };
+    let lines = existing.lines().collect::<Vec<&str>>();
+    if !lines.iter().any(|line| is(line, "BEGIN:VCALENDAR")) {
+        return Err(MergeError::NotACalendar);
+    }
+    let end = lines
+        .iter()
+        .rposition(|line| is(line, "END:VCALENDAR"))
+        .ok_or(MergeError::NotACalendar)?;
+    let mut merged: Vec<&str> = vec![];
+    let mut removed = 0;
+    let mut i = 0;
+    while i < end {
+        let close = is(lines[i], "BEGIN:VEVENT")
+            .then(|| lines[i..end].iter().position(|line| is(line, "END:VEVENT")))
+            .flatten();
+        match close {
+            Some(close) => {
+                let event = &lines[i..=i + close];
+                if generated_for(&event.join("\n"), time_table_id, &uids) {
+                    removed += 1;
+                } else {
+                    merged.extend(event);
+                }
+                i += close + 1;
+            }
+            None => {
+                merged.push(lines[i]);
+                i += 1;
+            }
+        }
+    }
+    let added = events.len();
+    merged.extend(events.into_iter().flatten());
+    merged.extend(&lines[end..]);
+    Ok(Merged {
+        ics: merged.join(newline) + newline,
+        removed,
+        added,
+    })
+}
+
+fn is(line: &str, content_line: &str) -> bool {
+    line.trim_end().eq_ignore_ascii_case(content_line)
+}
+
+/// Lines of every VEVENT, BEGIN and END included.
+fn events(ics: &str) -> Vec<Vec<&str>> {
+    let mut events: Vec<Vec<&str>> = vec![];
+    let mut event: Option<Vec<&str>> = None;
+    for line in ics.lines() {
+        if is(line, "BEGIN:VEVENT") {
+            event = Some(vec![]);
+        }
+        if let Some(event) = event.as_mut() {
+            event.push(line);
+        }
+        if is(line, "END:VEVENT") {
+            events.extend(event.take());
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data::{Course, Section, TimeTable, Timing};
+    use crate::campus::Campus;
+    use crate::ics::{make_calendar, TIME_TABLE_PROPERTY};
+    use chrono::{NaiveDate, Weekday};
+
+    const PERSONAL: &str = "BEGIN:VCALENDAR\nVERSION:2.0\nPRODID:-//personal//EN\n\
+BEGIN:VEVENT\nUID:dentist@example.com\nSUMMARY:Dentist\nDTSTART:20240902T100000Z\nEND:VEVENT\n\
+BEGIN:VEVENT\nUID:other-CSF111-lecture-1-Mon@chrono-to-ics\nSUMMARY:CS F111\nEND:VEVENT\n\
+END:VCALENDAR\n";
+
+    fn generated() -> Calendar {
+        generated_for_id("42")
+    }
+    fn generated_for_id(id: &str) -> Calendar {
+        let time_table = TimeTable::builder(
+            Campus::Hyderabad,
+            NaiveDate::from_ymd_opt(2024, 8, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 11, 30).unwrap(),
+        )
+        .id(id)
+        .course(
+            Course::builder("CS F111", "Computer Programming")
+                .lecture(Section::new(1).timing(Timing::new(Weekday::Mon, "F102", 2, 2)))
+                .build(),
+        )
+        .build()
+        .unwrap();
+        make_calendar(&time_table)
+    }
+
+    #[test]
+    fn merging_again_replaces_the_generated_events() {
+        let generated = generated();
+        let once = merge(PERSONAL, "42", &generated).unwrap();
+        assert_eq!(
+            (once.removed, once.added),
+            (0, events(&generated.to_string()).len())
+        );
+        assert!(once.added > 0);
+        let twice = merge(&once.ics, "42", &generated).unwrap();
+        assert_eq!(twice.removed, once.added);
+        assert_eq!(twice.ics, once.ics);
+    }
+
+    #[test]
+    fn keeps_events_of_others() {
+        let merged = merge(PERSONAL, "42", &generated()).unwrap();
+        assert!(merged.ics.contains("UID:dentist@example.com"));
+        // merged from another timetable
+        assert!(merged
+            .ics
+            .contains("UID:other-CSF111-lecture-1-Mon@chrono-to-ics"));
+        assert!(merged
+            .ics
+            .starts_with("BEGIN:VCALENDAR\nVERSION:2.0\nPRODID:-//personal//EN\n"));
+        assert!(merged.ics.ends_with("END:VEVENT\nEND:VCALENDAR\n"));
+    }
+
+    #[test]
+    fn keeps_timetables_with_a_longer_id() {
+        let longer = merge(PERSONAL, "abc-def", &generated_for_id("abc-def")).unwrap();
+        let merged = merge(&longer.ics, "abc", &generated_for_id("abc")).unwrap();
+        assert_eq!(merged.removed, 0);
+        assert_eq!(merged.ics.matches("UID:abc-def-").count(), longer.added);
+        // events merged before the timetable id was stored are matched by their uid
+        let legacy = merged
+            .ics
+            .lines()
+            .filter(|line| !line.starts_with(TIME_TABLE_PROPERTY))
+            .collect::<Vec<&str>>()
+            .join("\n");
+        let again = merge(&legacy, "abc", &generated_for_id("abc")).unwrap();
+        assert_eq!(again.removed, merged.added);
+        assert_eq!(again.ics.matches("UID:abc-def-").count(), longer.added);
+    }
+
+    #[test]
+    fn keeps_crlf() {
+        let existing = PERSONAL.replace('\n', "\r\n");
+        let merged = merge(&existing, "42", &generated()).unwrap();
+        assert!(merged.ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(!merged.ics.replace("\r\n", "").contains('\n'));
+    }
+
+    #[test]
+    fn not_a_calendar() {
+        assert_eq!(
+            merge("hello", "42", &generated()),
+            Err(MergeError::NotACalendar)
+        );
+    }
+
+    #[test]
+    fn events_with_begin_and_end() {
+        let events = events(PERSONAL);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].first(), Some(&"BEGIN:VEVENT"));
+        assert_eq!(events[0].last(), Some(&"END:VEVENT"));
+        assert_eq!(events[0][1], "UID:dentist@example.com");
+    }
+}
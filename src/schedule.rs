@@ -0,0 +1,219 @@
+//! Walks the semester day by day: holidays, midsem and day swaps applied.
+use crate::api::data::{Closure, Course, Section, SectionKind, TimeTable};
+use crate::campus::CampusProfile;
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+
+/// A single class: one timing of a section on a concrete date.
+#[derive(Debug, Clone, Copy)]
+pub struct Occurrence<'a> {
+    /// campus-local date of the class
+    pub date: NaiveDate,
+    pub course: &'a Course,
+    pub section: &'a Section,
+    pub kind: SectionKind,
+    pub room: &'a str,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// Every class between `from` and `to` (inclusive, campus-local dates) within classwork,
+/// in date order.
+pub(crate) fn occurrences(
+    time_table: &TimeTable,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> impl Iterator<Item = Occurrence<'_>> {
+    let profile = time_table.campus.profile();
+    let start = profile.local_date(&time_table.classwork_start).max(from);
+    let end = profile.local_date(&time_table.classwork_end).min(to);
+    let midsem = time_table
+        .midsem_dates
+        .map(|(start, end)| (profile.local_date(&start), profile.local_date(&end)));
+    let closures = time_table
+        .holidays
+        .iter()
+        .map(|holiday| (profile.local_date(&holiday.date), holiday.closure))
+        .collect::<Vec<(NaiveDate, Closure)>>();
+    let swaps = time_table
+        .time_table_changes
+        .iter()
+        .map(|change| (profile.local_date(&change.date), change.day))
+        .collect::<Vec<(NaiveDate, Weekday)>>();
+
+    start
+        .iter_days()
+        .take_while(move |date| *date <= end)
+        .flat_map(move |date| {
+            let mut day_closures = closures
+                .iter()
+                .filter(|(holiday, _)| *holiday == date)
+                .map(|(_, closure)| *closure)
+                .collect::<Vec<Closure>>();
+            if midsem.is_some_and(|(start, end)| start <= date && date <= end) {
+                day_closures.push(Closure::FullDay);
+            }
+            // a swapped day follows another weekday's timetable
+            let weekday = swaps
+                .iter()
+                .find(|(swap, _)| *swap == date)
+                .map_or(date.weekday(), |(_, day)| *day);
+            day_occurrences(time_table, &profile, date, weekday, day_closures)
+        })
+}
+
+fn day_occurrences<'a>(
+    time_table: &'a TimeTable,
+    profile: &CampusProfile,
+    date: NaiveDate,
+    weekday: Weekday,
+    closures: Vec<Closure>,
+) -> Vec<Occurrence<'a>> {
+    let mut day = time_table
+        .courses
+        .iter()
+        .flat_map(|course| {
+            course.sections().flat_map(move |(kind, section)| {
+                section
+                    .timings
+                    .iter()
+                    .map(move |timing| (course, kind, section, timing))
+            })
+        })
+        .filter(|(_, _, _, timing)| {
+            timing.day == weekday
+                && !closures
+                    .iter()
+                    .any(|closure| closure.cancels(timing.start, timing.end))
+        })
+        .filter_map(|(course, kind, section, timing)| {
+            Some(Occurrence {
+                date,
+                course,
+                section,
+                kind,
+                room: &timing.classroom,
+                start: profile.to_utc(date, profile.slots.start(timing.start))?,
+                end: profile.to_utc(date, profile.slots.end(timing.end))?,
+            })
+        })
+        .collect::<Vec<Occurrence>>();
+    day.sort_by_key(|occurrence| occurrence.start);
+    day
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data::{Holiday, Timing};
+    use crate::campus::Campus;
+    use chrono::{NaiveTime, TimeZone};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 8, day).unwrap()
+    }
+
+    // monday the 5th to friday the 16th
+    fn time_table(campus: Campus) -> crate::api::builder::TimeTableBuilder {
+        TimeTable::builder(campus, date(5), date(16)).course(
+            Course::builder("CS F111", "Computer Programming")
+                .lecture(Section::new(1).timing(Timing::new(Weekday::Mon, "F102", 2, 2)))
+                .tutorial(Section::new(1).timing(Timing::new(Weekday::Wed, "F105", 3, 4)))
+                .lab(
+                    Section::new(1)
+                        .timing(Timing::new(Weekday::Fri, "D311", 1, 1))
+                        .timing(Timing::new(Weekday::Fri, "D311", 5, 5)),
+                )
+                .build(),
+        )
+    }
+
+    fn dates(time_table: &TimeTable) -> Vec<(NaiveDate, SectionKind)> {
+        time_table
+            .occurrences(NaiveDate::MIN, NaiveDate::MAX)
+            .map(|occurrence| (occurrence.date, occurrence.kind))
+            .collect()
+    }
+
+    #[test]
+    fn weekly_classes_within_classwork() {
+        let time_table = time_table(Campus::Hyderabad).build().unwrap();
+        assert_eq!(
+            dates(&time_table),
+            vec![
+                (date(5), SectionKind::Lecture),
+                (date(7), SectionKind::Tutorial),
+                (date(9), SectionKind::Lab),
+                (date(9), SectionKind::Lab),
+                (date(12), SectionKind::Lecture),
+                (date(14), SectionKind::Tutorial),
+                (date(16), SectionKind::Lab),
+                (date(16), SectionKind::Lab),
+            ]
+        );
+        assert_eq!(time_table.occurrences(date(6), date(8)).count(), 1);
+    }
+
+    #[test]
+    fn slots_are_campus_wall_clock() {
+        for (campus, start, end) in [
+            (Campus::Hyderabad, (3, 30), (4, 20)),
+            (Campus::Dubai, (5, 0), (5, 50)),
+        ] {
+            let time_table = time_table(campus).build().unwrap();
+            let monday = &time_table.day(date(5))[0];
+            let utc = |(hour, minute)| Utc.with_ymd_and_hms(2024, 8, 5, hour, minute, 0).unwrap();
+            assert_eq!(
+                (monday.start, monday.end),
+                (utc(start), utc(end)),
+                "{}",
+                campus
+            );
+            assert_eq!(monday.room, "F102");
+        }
+    }
+
+    #[test]
+    fn holidays_and_midsem_cancel_classes() {
+        let profile = Campus::Hyderabad.profile();
+        let afternoon = Holiday::new("half day", profile.to_utc(date(9), NaiveTime::MIN).unwrap())
+            .closure(Closure::AfterSlot(4));
+        let time_table = time_table(Campus::Hyderabad)
+            .holiday_on("holiday", date(12))
+            .holiday(afternoon)
+            .midsem(date(14), date(16))
+            .build()
+            .unwrap();
+        assert_eq!(
+            dates(&time_table),
+            vec![
+                (date(5), SectionKind::Lecture),
+                (date(7), SectionKind::Tutorial),
+                (date(9), SectionKind::Lab),
+            ]
+        );
+        // the morning lab stays, the slot 5 one is cancelled
+        assert_eq!(
+            time_table.day(date(9))[0].start.time(),
+            NaiveTime::from_hms_opt(2, 30, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn swapped_days_follow_another_weekday() {
+        let time_table = time_table(Campus::Hyderabad)
+            .day_swap(date(10), Weekday::Mon)
+            .day_swap(date(14), Weekday::Fri)
+            .build()
+            .unwrap();
+        assert_eq!(time_table.day(date(10)).len(), 1);
+        assert_eq!(time_table.day(date(10))[0].kind, SectionKind::Lecture);
+        assert_eq!(
+            time_table
+                .day(date(14))
+                .iter()
+                .map(|occurrence| occurrence.kind)
+                .collect::<Vec<SectionKind>>(),
+            vec![SectionKind::Lab, SectionKind::Lab]
+        );
+    }
+}
@@ -0,0 +1,379 @@
+//! Serves timetables as calendar feeds, `GET /calendar/{timetable_id}.ics`, so calendar apps
+//! can subscribe to them and pick up changes.
+use crate::api::client::ApiError;
+use crate::api::data::TimeTable;
+use crate::api::link::parse_timetable_id;
+use crate::caldav::fnv1a;
+use crate::converter::{ConvertError, Converter};
+use std::collections::HashMap;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use std::{fmt, io, thread};
+use tiny_http::{Header, Method, Request, Response};
+
+/// A generated calendar.
+#[derive(Debug, Clone)]
+struct Feed {
+    time_table: TimeTable,
+    ics: String,
+    etag: String,
+    generated: Instant,
+}
+impl Feed {
+    /// The etag is stable across restarts and upgrades, so subscribed apps don't download
+    /// an unchanged calendar again.
+    fn new(time_table: TimeTable, ics: String) -> Self {
+        Self {
+            time_table,
+            etag: format!("\"{:016x}\"", fnv1a(ics.as_bytes())),
+            ics,
+            generated: Instant::now(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ServerError {
+    Bind(io::Error),
+}
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bind(e) => write!(f, "unable to start the server: {}", e),
+        }
+    }
+}
+impl std::error::Error for ServerError {}
+
+/// Converts timetables on request with a [`Converter`]. Calendars are kept for `max_age`
+/// before the timetable is fetched again, and served stale while the chrono api is down.
+pub struct Server {
+    server: tiny_http::Server,
+    converter: Converter,
+    max_age: Duration,
+    workers: usize,
+    max_feeds: usize,
+    feeds: Mutex<HashMap<String, Feed>>,
+    /// held while an id is converted, so requests for it wait for that conversion
+    converting: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+impl Server {
+    /// Listens on `addr`, port 0 picks a free one, see [`Server::local_addr`].
+    pub fn bind(addr: impl ToSocketAddrs, converter: Converter) -> Result<Self, ServerError> {
+        let server =
+            tiny_http::Server::http(addr).map_err(|e| ServerError::Bind(io::Error::other(e)))?;
+        Ok(Self {
+            server,
+            converter,
+            max_age: Duration::from_secs(15 * 60),
+            workers: 8,
+            max_feeds: 256,
+            feeds: Mutex::new(HashMap::new()),
+            converting: Mutex::new(HashMap::new()),
+        })
+    }
+    /// 15 minutes by default, also sent as the `Cache-Control` max-age.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+    /// Requests answered at the same time, 8 by default. Conversions wait on the chrono api
+    /// so this is more than the cores.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+    /// Calendars kept at most, 256 by default. The one generated longest ago makes room.
+    pub fn max_feeds(mut self, max_feeds: usize) -> Self {
+        self.max_feeds = max_feeds.max(1);
+        self
+    }
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+    /// Answers requests on [`Server::workers`] threads until the process ends.
+    pub fn run(&self) {
+        thread::scope(|scope| {
+            for _ in 0..self.workers {
+                scope.spawn(|| {
+                    // recv only fails once the listener is gone
+                    while let Ok(request) = self.server.recv() {
+                        self.respond(request);
+                    }
+                });
+            }
+        });
+    }
+    fn respond(&self, request: Request) {
+        let response = match request.method() {
+            Method::Get | Method::Head => self.route(&request),
+            _ => text(405, "only GET is supported".to_string()),
+        };
+        // the client went away, nothing to do
+        let _ = request.respond(response);
+    }
+    fn route(&self, request: &Request) -> Response<io::Cursor<Vec<u8>>> {
+        let path = request.url().split('?').next().unwrap_or_default();
+        let Some(id) = path
+            .strip_prefix("/calendar/")
+            .and_then(|file| file.strip_suffix(".ics"))
+            .and_then(|id| parse_timetable_id(id).ok())
+        else {
+            return text(
+                404,
+                "not found, try /calendar/{timetable_id}.ics".to_string(),
+            );
+        };
+        let feed = match self.feed(&id) {
+            Ok(feed) => feed,
+            Err(ConvertError::InvalidLink(e)) => return text(404, e.to_string()),
+            Err(ConvertError::Fetch(ApiError::Network(e)))
+                if e.status() == Some(reqwest::StatusCode::NOT_FOUND) =>
+            {
+                return text(404, format!("no timetable {}", id))
+            }
+            Err(e) => return text(502, e.to_string()),
+        };
+        let if_none_match = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("If-None-Match"))
+            .map(|header| header.value.as_str());
+        let response = match if_none_match == Some(feed.etag.as_str()) {
+            true => Response::from_data(vec![]).with_status_code(304),
+            false => Response::from_data(feed.ics.into_bytes())
+                .with_header(header("Content-Type", "text/calendar; charset=utf-8")),
+        };
+        response
+            .with_header(header("ETag", &feed.etag))
+            .with_header(header(
+                "Cache-Control",
+                &format!("max-age={}", self.max_age.as_secs()),
+            ))
+    }
+    /// The kept calendar while it is fresh, otherwise converted again. Requests for an id
+    /// that is being converted wait for that conversion instead of starting their own.
+    fn feed(&self, id: &str) -> Result<Feed, ConvertError> {
+        if let Some(feed) = self.kept(id).filter(|feed| self.is_fresh(feed)) {
+            return Ok(feed);
+        }
+        let converting = self
+            .converting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(id.to_string())
+            .or_default()
+            .clone();
+        let feed = {
+            let _converting = converting.lock().unwrap_or_else(PoisonError::into_inner);
+            self.convert(id)
+        };
+        let mut ids = self
+            .converting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // only the map and this request hold it, nobody else is waiting
+        if Arc::strong_count(&converting) == 2 {
+            ids.remove(id);
+        }
+        feed
+    }
+    fn kept(&self, id: &str) -> Option<Feed> {
+        self.feeds
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(id)
+            .cloned()
+    }
+    fn is_fresh(&self, feed: &Feed) -> bool {
+        feed.generated.elapsed() < self.max_age
+    }
+    /// Converts `id` unless a request it waited for already did. A failed conversion falls
+    /// back to the kept calendar when there is one.
+    fn convert(&self, id: &str) -> Result<Feed, ConvertError> {
+        let kept = self.kept(id);
+        if let Some(feed) = kept.as_ref().filter(|feed| self.is_fresh(feed)) {
+            return Ok(feed.clone());
+        }
+        let feed = match (self.converter.convert(id), kept) {
+            // the ics has new timestamps, keep the old one so the etag still matches
+            (Ok(conversion), Some(kept)) if conversion.time_table == kept.time_table => Feed {
+                generated: Instant::now(),
+                ..kept
+            },
+            (Ok(conversion), _) => {
+                let ics = conversion.calendar.to_string();
+                Feed::new(conversion.time_table, ics)
+            }
+            (Err(e), kept) => return kept.ok_or(e),
+        };
+        let mut feeds = self.feeds.lock().unwrap_or_else(PoisonError::into_inner);
+        if !feeds.contains_key(id) && feeds.len() >= self.max_feeds {
+            let oldest = feeds
+                .iter()
+                .min_by_key(|(_, feed)| feed.generated)
+                .map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                feeds.remove(&oldest);
+            }
+        }
+        feeds.insert(id.to_string(), feed.clone());
+        Ok(feed)
+    }
+}
+
+fn header(field: &str, value: &str) -> Header {
+    // only called with ascii names and values
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap()
+}
+fn text(status: u16, body: String) -> Response<io::Cursor<Vec<u8>>> {
+    // from_string already sets a text/plain content type
+    Response::from_string(body).with_status_code(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::cache::Cache;
+    use crate::api::holidays::HolidayData;
+    use crate::api::http::HttpOptions;
+    use crate::ics::CalendarOptions;
+    use reqwest::blocking::Client;
+    use reqwest::StatusCode;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const COURSES: &str = r#"[{"id": "c1", "code": "CS F111", "name": "Computer Programming"}]"#;
+    const TIMETABLE: &str = r#"{
+        "id": "42", "name": "mine", "acadYear": 2024, "semester": 1,
+        "sections": [{
+            "courseId": "c1", "type": "L", "number": 1, "instructors": ["A"],
+            "roomTime": ["CS F111:F102:M:2", "CS F111:F102:W:2"]
+        }],
+        "timings": [], "examTimes": []
+    }"#;
+
+    /// A chrono api with one course and timetable 42, which is slow to answer and counts how
+    /// often it was fetched.
+    fn chrono_api(fetched: Arc<AtomicUsize>) -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let fetched = fetched.clone();
+                thread::spawn(move || {
+                    let response = match request.url() {
+                        "/course" => text(200, COURSES.to_string()),
+                        "/timetable/42" => {
+                            fetched.fetch_add(1, Ordering::SeqCst);
+                            thread::sleep(Duration::from_millis(200));
+                            text(200, TIMETABLE.to_string())
+                        }
+                        _ => text(404, "not found".to_string()),
+                    };
+                    let _ = request.respond(response);
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    /// Serves the timetables of [`chrono_api`] without the holiday editor's override.
+    fn feeds(name: &str, fetched: Arc<AtomicUsize>) -> String {
+        let cache = Cache::new(std::env::temp_dir().join(format!(
+            "chrono-to-ics-{}-{}",
+            name,
+            std::process::id()
+        )));
+        let _ = cache.clear();
+        let converter = Converter::with_http(
+            CalendarOptions::default().holidays(HolidayData::Bundled),
+            cache,
+            HttpOptions {
+                api_url: chrono_api(fetched),
+                retries: 0,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .holiday_override(None);
+        let server = Server::bind("127.0.0.1:0", converter).unwrap().workers(4);
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn serves_calendars() {
+        let url = feeds("serve", Arc::default());
+        let client = Client::new();
+        let response = client
+            .get(format!("{}/calendar/42.ics", url))
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-type"],
+            "text/calendar; charset=utf-8"
+        );
+        assert_eq!(response.headers()["cache-control"], "max-age=900");
+        let etag = response.headers()["etag"].to_str().unwrap().to_string();
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+        let ics = response.text().unwrap();
+        assert_eq!(etag, format!("\"{:016x}\"", fnv1a(ics.as_bytes())));
+        assert!(ics.starts_with("BEGIN:VCALENDAR"));
+        assert!(ics.contains("CS F111"));
+
+        let response = client
+            .get(format!("{}/calendar/42.ics", url))
+            .header("If-None-Match", &etag)
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+        assert_eq!(response.text().unwrap(), "");
+    }
+
+    #[test]
+    fn not_found() {
+        let url = feeds("not-found", Arc::default());
+        let client = Client::new();
+        for path in [
+            "/",
+            "/calendar/42",
+            "/calendar/not%20an%20id.ics",
+            "/calendar/7.ics",
+        ] {
+            let response = client.get(format!("{}{}", url, path)).send().unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", path);
+        }
+        let response = client
+            .post(format!("{}/calendar/42.ics", url))
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[test]
+    fn converts_once_for_concurrent_requests() {
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let url = feeds("concurrent", fetched.clone());
+        let etags: Vec<String> = thread::scope(|scope| {
+            let requests: Vec<_> = (0..3)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let response = Client::new()
+                            .get(format!("{}/calendar/42.ics", url))
+                            .send()
+                            .unwrap();
+                        assert_eq!(response.status(), StatusCode::OK);
+                        response.headers()["etag"].to_str().unwrap().to_string()
+                    })
+                })
+                .collect();
+            requests.into_iter().map(|r| r.join().unwrap()).collect()
+        });
+        assert_eq!(fetched.load(Ordering::SeqCst), 1);
+        assert!(etags.iter().all(|etag| *etag == etags[0]));
+    }
+}
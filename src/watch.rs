@@ -0,0 +1,268 @@
+//! Keeps an ics file in step with a timetable, e.g. one in a synced folder a calendar app
+//! subscribes to.
+use crate::api::client::{ApiClient, ApiError};
+use crate::api::data::TimeTable;
+use crate::diff::TimeTableDiff;
+use crate::ics::{self, CalendarOptions};
+use chrono::Utc;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fmt, thread};
+
+/// What a check did.
+#[derive(Debug)]
+pub enum Update {
+    Unchanged,
+    /// the ics was written, with the changes since the last write (none for the first one)
+    Written(Option<Changes>),
+}
+
+#[derive(Debug)]
+pub struct Changes {
+    pub time_table: TimeTableDiff,
+    /// holidays, day swaps, classwork or midsem dates changed
+    pub holidays: bool,
+}
+impl fmt::Display for Changes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines: Vec<String> = vec![];
+        if !self.time_table.is_empty() {
+            lines.push(self.time_table.to_string());
+        }
+        if self.holidays {
+            lines.push("holiday data changed".to_string());
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[derive(Debug)]
+pub enum WatchError {
+    Fetch(ApiError),
+    InvalidTimeTableData,
+    Write(io::Error),
+}
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fetch(e) => write!(f, "unable to fetch data: {}", e),
+            Self::InvalidTimeTableData => write!(f, "timetable is invalid"),
+            Self::Write(e) => write!(f, "unable to write data: {}", e),
+        }
+    }
+}
+impl std::error::Error for WatchError {}
+
+/// Fetches the timetable and holidays through an [`ApiClient`] and rewrites the ics only
+/// when the linked timetable changed.
+#[derive(Debug)]
+pub struct Watcher {
+    api: ApiClient,
+    output: PathBuf,
+    options: CalendarOptions,
+    log: Option<PathBuf>,
+    taken_restricted: Option<Vec<String>>,
+    /// the timetable last written, normalized, kept in `state` across restarts
+    written: Option<TimeTable>,
+    state: PathBuf,
+    /// the next check uses what `api` already fetched instead of fetching again
+    fetched: bool,
+}
+impl Watcher {
+    /// The first check uses the timetable `api` already holds, if it has one. The timetable
+    /// written last is saved next to `output`, so after a restart the ics is only rewritten
+    /// when something changed in between.
+    pub fn new(api: ApiClient, output: impl Into<PathBuf>, options: CalendarOptions) -> Self {
+        let output = output.into();
+        let state = state_path(&output);
+        let written = TimeTable::load(&state)
+            .ok()
+            .filter(|written| written.id == api.id);
+        Self {
+            fetched: api.timetable.is_some(),
+            api,
+            output,
+            options,
+            log: None,
+            taken_restricted: None,
+            written,
+            state,
+        }
+    }
+    /// File every write is appended to, with the time and what changed.
+    pub fn log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.log = Some(path.into());
+        self
+    }
+    /// Restricted holidays taken, see [`TimeTable::keep_restricted_holidays`]. All of them
+    /// are applied when not set.
+    pub fn taken_restricted(mut self, taken: Option<Vec<String>>) -> Self {
+        self.taken_restricted = taken;
+        self
+    }
+    pub fn api(&self) -> &ApiClient {
+        &self.api
+    }
+    /// Fetches once and writes the ics if it is missing or the timetable changed.
+    pub fn check(&mut self) -> Result<Update, WatchError> {
+        if !std::mem::take(&mut self.fetched) {
+            self.api.refresh().map_err(WatchError::Fetch)?;
+        }
+        self.api
+            .update_time_table()
+            .ok_or(WatchError::InvalidTimeTableData)?;
+        let mut time_table = self
+            .api
+            .timetable
+            .clone()
+            .ok_or(WatchError::InvalidTimeTableData)?;
+        if let Some(taken) = &self.taken_restricted {
+            time_table.keep_restricted_holidays(taken);
+        }
+        let time_table = normalize(time_table);
+        if self.written.as_ref() == Some(&time_table) && self.output.exists() {
+            return Ok(Update::Unchanged);
+        }
+        let calendar = ics::calendar(&time_table, &self.options).to_string();
+        write_atomic(&self.output, &calendar).map_err(WatchError::Write)?;
+        // losing it only means the next start rewrites the ics without reporting changes
+        if let Ok(json) = time_table.to_json() {
+            let _ = write_atomic(&self.state, &json);
+        }
+        let changes = self.written.as_ref().map(|written| Changes {
+            time_table: TimeTableDiff::new(written, &time_table),
+            holidays: written.holidays != time_table.holidays
+                || written.time_table_changes != time_table.time_table_changes
+                || written.midsem_dates != time_table.midsem_dates
+                || (written.classwork_start, written.classwork_end)
+                    != (time_table.classwork_start, time_table.classwork_end),
+        });
+        if let Some(log) = &self.log {
+            append_log(log, &self.output, changes.as_ref()).map_err(WatchError::Write)?;
+        }
+        self.written = Some(time_table);
+        Ok(Update::Written(changes))
+    }
+    /// Checks every `interval`, forever. Failed checks are reported like the others and
+    /// retried at the next one.
+    pub fn run(
+        &mut self,
+        interval: Duration,
+        mut report: impl FnMut(Result<Update, WatchError>),
+    ) -> ! {
+        loop {
+            report(self.check());
+            thread::sleep(interval);
+        }
+    }
+}
+
+/// Order of courses, holidays and timings doesn't matter to the calendar, and warnings
+/// aren't part of it.
+fn normalize(mut time_table: TimeTable) -> TimeTable {
+    time_table.warnings.clear();
+    time_table.courses.sort_by(|a, b| a.code.cmp(&b.code));
+    for course in time_table.courses.iter_mut() {
+        for section in [&mut course.lecture, &mut course.tutorial, &mut course.lab]
+            .into_iter()
+            .flatten()
+        {
+            section
+                .timings
+                .sort_by_key(|timing| (timing.day.num_days_from_monday(), timing.start));
+            section.instructors.sort();
+        }
+    }
+    time_table
+        .holidays
+        .sort_by(|a, b| (a.date, &a.name).cmp(&(b.date, &b.name)));
+    time_table
+        .time_table_changes
+        .sort_by_key(|change| change.date);
+    time_table
+}
+
+// timetable.ics -> .timetable.ics.json
+fn state_path(output: &Path) -> PathBuf {
+    let name = output.file_name().unwrap_or_default().to_string_lossy();
+    output.with_file_name(format!(".{}.json", name))
+}
+
+/// Writes next to `path` and renames over it, so readers never see half a file.
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.tmp", name));
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp, path)
+}
+
+// 2024-08-20T10:00:00Z timetable.ics written
+//   CS F111 lecture 1 on Mon slot 2 moved from F102 to F105
+fn append_log(log: &Path, output: &Path, changes: Option<&Changes>) -> io::Result<()> {
+    let mut entry = format!(
+        "{} {} written\n",
+        Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+        output.display()
+    );
+    if let Some(changes) = changes {
+        entry.extend(
+            changes
+                .to_string()
+                .lines()
+                .map(|line| format!("  {}\n", line)),
+        );
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)?
+        .write_all(entry.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data::{Course, Section, Timing};
+    use crate::campus::Campus;
+    use chrono::{NaiveDate, Weekday};
+
+    #[test]
+    fn state_is_hidden_next_to_the_output() {
+        assert_eq!(
+            state_path(Path::new("sync/timetable.ics")),
+            PathBuf::from("sync/.timetable.ics.json")
+        );
+    }
+
+    #[test]
+    fn saved_state_compares_equal() {
+        let time_table = TimeTable::builder(
+            Campus::Hyderabad,
+            NaiveDate::from_ymd_opt(2024, 8, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 11, 30).unwrap(),
+        )
+        .id("42")
+        .course(
+            Course::builder("CS F111", "Computer Programming")
+                .lecture(
+                    Section::new(1)
+                        .instructor("B")
+                        .instructor("A")
+                        .timing(Timing::new(Weekday::Wed, "F102", 2, 2))
+                        .timing(Timing::new(Weekday::Mon, "F102", 2, 2)),
+                )
+                .build(),
+        )
+        .holiday_on("Diwali", NaiveDate::from_ymd_opt(2024, 10, 31).unwrap())
+        .day_swap(NaiveDate::from_ymd_opt(2024, 9, 7).unwrap(), Weekday::Mon)
+        .build()
+        .unwrap();
+        let written = normalize(time_table);
+        let saved = TimeTable::from_json(&written.to_json().unwrap()).unwrap();
+        assert_eq!(normalize(saved), written);
+    }
+}